@@ -8,7 +8,8 @@ use std::path::Path;
 fn main() {
     let mut c = Captcha::new();
     c.add_random_chars(9)
-        .view(280, 160)
+        .view(360, 160)
+        .expect("view failed")
         .apply_filter(Noise::new(0.05))
         .expect("Noise filter failed")
         .apply_filter(Wave::new(4.0, 20.0))
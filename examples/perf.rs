@@ -26,7 +26,7 @@ fn main() {
                     .as_str(),
                 ))
                 .unwrap();*/
-                gen(Difficulty::Easy).as_tuple();
+                gen(Difficulty::Easy).as_tuple().expect("as_tuple failed");
             }
             println!("done {:?} ms", b.elapsed().whole_milliseconds());
         });
@@ -9,12 +9,14 @@ fn main() {
     println!("{:?}", c.supported_chars());
 
     c.set_chars(&['a', 'b'])
+        .expect("set_chars failed")
         .add_random_chars(5)
         .apply_filter(Noise::new(0.2))
         .expect("Noise filter failed")
         .apply_filter(Wave::new(2.0, 20.0))
         .expect("Wave filter failed")
         .view(220, 120)
+        .expect("view failed")
         .apply_filter(
             Cow::new()
                 .min_radius(40)
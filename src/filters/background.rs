@@ -0,0 +1,273 @@
+use rand::{Rng, RngCore};
+use std::f32::consts::TAU;
+
+use super::SerdeColor;
+use filters::{Context, Filter, ValidationPolicy};
+use images::{Image, Pixl};
+
+/// The pattern of a `Background`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Pattern {
+    /// Changes from `from` to `to` along the direction `angle` in degrees. An angle of 0 runs
+    /// from the left to the right edge, positive angles turn clockwise.
+    LinearGradient {
+        from: SerdeColor,
+        to: SerdeColor,
+        angle: f32,
+    },
+    /// Changes from `inner` at the center to `outer` at the farthest corner. The center is given
+    /// as fraction of the width and the height of the image.
+    RadialGradient {
+        inner: SerdeColor,
+        outer: SerdeColor,
+        center: (f32, f32),
+    },
+    /// Alternating stripes of the colors `a` and `b` which are `width` pixels wide. An angle of 0
+    /// gives vertical stripes, positive angles turn clockwise.
+    Stripes {
+        a: SerdeColor,
+        b: SerdeColor,
+        width: u32,
+        angle: f32,
+    },
+    /// Alternating squares of the colors `a` and `b` whose sides are `size` pixels long.
+    Checkerboard {
+        a: SerdeColor,
+        b: SerdeColor,
+        size: u32,
+    },
+    /// Perlin noise between the colors `from` and `to`. `scale` is the size in pixels of the
+    /// coarsest features and each further octave adds details of half the size.
+    Noise {
+        from: SerdeColor,
+        to: SerdeColor,
+        scale: f32,
+        octaves: u32,
+    },
+}
+
+/// Puts a pattern under the content of the image.
+///
+/// The image is assumed to be drawn on white, so that the pattern replaces the white background
+/// while the text and everything else which has already been drawn stays on top.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Background {
+    pattern: Pattern,
+}
+
+impl Background {
+    pub fn new(pattern: Pattern) -> Background {
+        Background { pattern }
+    }
+
+    /// Returns both colors of the pattern.
+    fn colors(&self) -> [&SerdeColor; 2] {
+        match self.pattern {
+            Pattern::LinearGradient {
+                ref from, ref to, ..
+            }
+            | Pattern::Noise {
+                ref from, ref to, ..
+            } => [from, to],
+            Pattern::RadialGradient {
+                ref inner,
+                ref outer,
+                ..
+            } => [inner, outer],
+            Pattern::Stripes { ref a, ref b, .. } | Pattern::Checkerboard { ref a, ref b, .. } => {
+                [a, b]
+            }
+        }
+    }
+
+    /// Returns the color between `a` and `b` at `t` between 0 and 1.
+    fn mix(a: &SerdeColor, b: &SerdeColor, t: f32) -> Pixl {
+        let t = t.clamp(0.0, 1.0);
+        let v = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Pixl::new(v(a.r, b.r), v(a.g, b.g), v(a.b, b.b))
+    }
+
+    /// Returns the distance of the point `x`, `y` from the origin along the direction `angle` in
+    /// degrees.
+    fn project(x: u32, y: u32, angle: f32) -> f32 {
+        let (s, c) = angle.to_radians().sin_cos();
+        x as f32 * c + y as f32 * s
+    }
+
+    /// Returns Perlin noise for an image of size `w` x `h` normalized to values between 0 and 1.
+    fn perlin(rng: &mut dyn RngCore, w: u32, h: u32, scale: f32, octaves: u32) -> Vec<f32> {
+        let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        let mut field = vec![0.0f32; w as usize * h as usize];
+        let (mut cell, mut amp) = (scale, 1.0);
+
+        for _ in 0..octaves {
+            let gw = (w as f32 / cell).ceil() as usize + 2;
+            let gh = (h as f32 / cell).ceil() as usize + 2;
+            let gradients: Vec<(f32, f32)> = (0..gw * gh)
+                .map(|_| {
+                    let (s, c) = rng.gen_range(0.0..TAU).sin_cos();
+                    (c, s)
+                })
+                .collect();
+            // the dot product of the gradient at a lattice point and the offset from it
+            let dot = |gx: usize, gy: usize, dx: f32, dy: f32| {
+                let g = gradients[gy * gw + gx];
+                g.0 * dx + g.1 * dy
+            };
+
+            for y in 0..h {
+                for x in 0..w {
+                    let (fx, fy) = (x as f32 / cell, y as f32 / cell);
+                    let (gx, gy) = (fx as usize, fy as usize);
+                    let (tx, ty) = (fx - gx as f32, fy - gy as f32);
+                    let top = dot(gx, gy, tx, ty)
+                        + fade(tx) * (dot(gx + 1, gy, tx - 1.0, ty) - dot(gx, gy, tx, ty));
+                    let bottom = dot(gx, gy + 1, tx, ty - 1.0)
+                        + fade(tx)
+                            * (dot(gx + 1, gy + 1, tx - 1.0, ty - 1.0)
+                                - dot(gx, gy + 1, tx, ty - 1.0));
+                    field[(y * w + x) as usize] += amp * (top + fade(ty) * (bottom - top));
+                }
+            }
+            cell = (cell / 2.0).max(1.0);
+            amp /= 2.0;
+        }
+
+        let lo = field.iter().cloned().fold(f32::MAX, f32::min);
+        let hi = field.iter().cloned().fold(f32::MIN, f32::max);
+        let range = if hi > lo { hi - lo } else { 1.0 };
+        field.iter().map(|v| (v - lo) / range).collect()
+    }
+}
+
+#[typetag::serde]
+impl Filter for Background {
+    fn apply(&self, i: &mut Image, ctx: &mut Context) -> Result<(), super::Error> {
+        let (w, h) = (i.width(), i.height());
+        match self.pattern {
+            Pattern::LinearGradient {
+                ref from,
+                ref to,
+                angle,
+            } => {
+                let r = (w - 1, h - 1);
+                let corners = [(0, 0), (r.0, 0), (0, r.1), r];
+                let p: Vec<f32> = corners
+                    .iter()
+                    .map(|&(x, y)| Self::project(x, y, angle))
+                    .collect();
+                let lo = p.iter().cloned().fold(f32::MAX, f32::min);
+                let hi = p.iter().cloned().fold(f32::MIN, f32::max);
+                let range = (hi - lo).max(1.0);
+                i.fill_under(|x, y| Self::mix(from, to, (Self::project(x, y, angle) - lo) / range));
+            }
+            Pattern::RadialGradient {
+                ref inner,
+                ref outer,
+                center,
+            } => {
+                let cx = center.0 * (w - 1) as f32;
+                let cy = center.1 * (h - 1) as f32;
+                let dx = cx.max((w - 1) as f32 - cx);
+                let dy = cy.max((h - 1) as f32 - cy);
+                let r = (dx * dx + dy * dy).sqrt().max(1.0);
+                i.fill_under(|x, y| {
+                    let d = ((x as f32 - cx).powi(2) + (y as f32 - cy).powi(2)).sqrt();
+                    Self::mix(inner, outer, d / r)
+                });
+            }
+            Pattern::Stripes {
+                ref a,
+                ref b,
+                width,
+                angle,
+            } => {
+                let (a, b) = (a.to_pixl(), b.to_pixl());
+                i.fill_under(|x, y| {
+                    let stripe = (Self::project(x, y, angle) / width as f32).floor() as i64;
+                    if stripe.rem_euclid(2) == 0 {
+                        a
+                    } else {
+                        b
+                    }
+                });
+            }
+            Pattern::Checkerboard { ref a, ref b, size } => {
+                let (a, b) = (a.to_pixl(), b.to_pixl());
+                i.fill_under(|x, y| if (x / size + y / size) % 2 == 0 { a } else { b });
+            }
+            Pattern::Noise {
+                ref from,
+                ref to,
+                scale,
+                octaves,
+            } => {
+                let field = Self::perlin(ctx.rng(), w, h, scale, octaves);
+                i.fill_under(|x, y| Self::mix(from, to, field[(y * w + x) as usize]));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn cost(&self, viewbox: (u32, u32)) -> u64 {
+        let pixels = super::pixels(viewbox);
+        match self.pattern {
+            // each octave interpolates between four gradients per pixel
+            Pattern::Noise { octaves, .. } => pixels.saturating_mul(1 + 4 * octaves as u64),
+            _ => pixels,
+        }
+    }
+
+    fn validate(
+        &self,
+        _viewbox: (u32, u32),
+        policy: &ValidationPolicy,
+    ) -> Result<(), super::Error> {
+        let invalid = |field, reason: &str| {
+            Err(super::Error::invalid_filter_params(
+                "Background",
+                field,
+                reason,
+            ))
+        };
+
+        // the darkest channel decides how close the background comes to the black text
+        let darkness = |c: &&SerdeColor| 255 - c.r.min(c.g).min(c.b);
+        if let Some(d) = self.colors().iter().map(darkness).max() {
+            if d > policy.max_darkness {
+                return invalid(
+                    "color",
+                    &format!(
+                        "must be at most {} dark, 255 - darkest channel is {}",
+                        policy.max_darkness, d
+                    ),
+                );
+            }
+        }
+
+        match self.pattern {
+            Pattern::LinearGradient { angle, .. } | Pattern::Stripes { angle, .. }
+                if !angle.is_finite() =>
+            {
+                invalid("angle", "must be a finite number")
+            }
+            Pattern::RadialGradient { center, .. }
+                if !(0.0..=1.0).contains(&center.0) || !(0.0..=1.0).contains(&center.1) =>
+            {
+                invalid("center", "must be between 0.0 and 1.0")
+            }
+            Pattern::Stripes { width: 0, .. } => invalid("width", "must be greater than 0"),
+            Pattern::Checkerboard { size: 0, .. } => invalid("size", "must be greater than 0"),
+            Pattern::Noise { scale, .. } if !(1.0..=65535.0).contains(&scale) => {
+                invalid("scale", "must be between 1.0 and 65535.0")
+            }
+            Pattern::Noise { octaves, .. } if octaves == 0 || octaves > 8 => {
+                invalid("octaves", "must be between 1 and 8")
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use std::cmp::min;
+
+use filters::convolve::{box_kernel, convolve, gaussian_kernel, MIN_SIGMA};
+use filters::{Context, Filter, ValidationPolicy};
+use images::{Image, Pixl};
+use Geometry;
+
+/// Convolves all channels and the coverage with text of the pixels within `g` (inclusive) with
+/// `kernel`.
+fn blur(i: &mut Image, g: &Geometry, kernel: &[f32]) {
+    let right = min(g.right, i.width() - 1);
+    let bottom = min(g.bottom, i.height() - 1);
+    if g.left > right || g.top > bottom {
+        return;
+    }
+    let (w, h) = ((right - g.left + 1) as usize, (bottom - g.top + 1) as usize);
+
+    let mut channels: Vec<Vec<f32>> = (0..5).map(|_| Vec::with_capacity(w * h)).collect();
+    for y in g.top..=bottom {
+        for x in g.left..=right {
+            let [r, gr, b, a] = i.get_pixel(x, y).rgba();
+            for (c, v) in channels
+                .iter_mut()
+                .zip(&[r, gr, b, a, i.text_coverage(x, y)])
+            {
+                c.push(*v as f32);
+            }
+        }
+    }
+    let channels: Vec<Vec<f32>> = channels.iter().map(|c| convolve(c, w, h, kernel)).collect();
+
+    for (k, (y, x)) in (g.top..=bottom)
+        .flat_map(|y| (g.left..=right).map(move |x| (y, x)))
+        .enumerate()
+    {
+        let v = |c: usize| channels[c][k].round().clamp(0.0, 255.0) as u8;
+        i.put_pixel(x, y, Pixl::new_with_alpha(v(0), v(1), v(2), v(3)));
+        i.set_text_coverage(x, y, v(4));
+    }
+}
+
+/// Returns the area of the image which is blurred.
+fn area(g: &Option<Geometry>, i: &Image) -> Geometry {
+    match g {
+        Some(g) => g.clone(),
+        None => Geometry::new(0, i.width() - 1, 0, i.height() - 1),
+    }
+}
+
+/// Validates the area to which a blur is restricted.
+fn validate_area(
+    filter: &'static str,
+    g: &Option<Geometry>,
+    viewbox: (u32, u32),
+) -> Result<(), super::Error> {
+    let invalid = |reason: &str| Err(super::Error::invalid_filter_params(filter, "area", reason));
+    if let Some(g) = g {
+        if g.left > g.right || g.top > g.bottom {
+            return invalid("left must not be greater than right and top not greater than bottom");
+        }
+        if g.right >= viewbox.0 || g.bottom >= viewbox.1 {
+            return invalid("must be within the viewbox");
+        }
+    }
+    Ok(())
+}
+
+/// Returns the cost of convolving the four channels of `area` with a kernel of size `k`.
+fn cost(g: &Option<Geometry>, viewbox: (u32, u32), k: u64) -> u64 {
+    let pixels = match g {
+        Some(g) => super::pixels((
+            g.right.saturating_sub(g.left) + 1,
+            g.bottom.saturating_sub(g.top) + 1,
+        )),
+        None => super::pixels(viewbox),
+    };
+    // two passes over four channels and the coverage with text
+    (10 * k).saturating_mul(pixels)
+}
+
+/// Smooths the image with a Gaussian filter.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GaussianBlur {
+    sigma: f32,
+    area: Option<Geometry>,
+}
+
+impl GaussianBlur {
+    /// Returns a Gaussian filter with the standard deviation `sigma` in pixels.
+    pub fn new(sigma: f32) -> GaussianBlur {
+        GaussianBlur { sigma, area: None }
+    }
+
+    /// Restricts the filter to the given area (right + bottom = inclusive).
+    pub fn area(self, g: Geometry) -> GaussianBlur {
+        GaussianBlur {
+            area: Some(g),
+            ..self
+        }
+    }
+}
+
+#[typetag::serde]
+impl Filter for GaussianBlur {
+    fn apply(&self, i: &mut Image, _ctx: &mut Context) -> Result<(), super::Error> {
+        let g = area(&self.area, i);
+        blur(i, &g, &gaussian_kernel(self.sigma));
+        Ok(())
+    }
+
+    fn validate(
+        &self,
+        viewbox: (u32, u32),
+        _policy: &ValidationPolicy,
+    ) -> Result<(), super::Error> {
+        let max = min(viewbox.0, viewbox.1) as f32 / 2.0;
+        if self.sigma.is_nan() || self.sigma < MIN_SIGMA || self.sigma > max {
+            return Err(super::Error::invalid_filter_params(
+                "GaussianBlur",
+                "sigma",
+                format!(
+                    "must be at least {} and at most half of the viewbox",
+                    MIN_SIGMA
+                ),
+            ));
+        }
+        validate_area("GaussianBlur", &self.area, viewbox)
+    }
+
+    fn cost(&self, viewbox: (u32, u32)) -> u64 {
+        let k = 2 * (3.0 * self.sigma).ceil().max(1.0) as u64 + 1;
+        cost(&self.area, viewbox, k)
+    }
+}
+
+/// Smooths the image by averaging the pixels within a square.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoxBlur {
+    radius: u32,
+    area: Option<Geometry>,
+}
+
+impl BoxBlur {
+    /// Returns a filter which averages squares of `2 * radius + 1` pixels.
+    pub fn new(radius: u32) -> BoxBlur {
+        BoxBlur { radius, area: None }
+    }
+
+    /// Restricts the filter to the given area (right + bottom = inclusive).
+    pub fn area(self, g: Geometry) -> BoxBlur {
+        BoxBlur {
+            area: Some(g),
+            ..self
+        }
+    }
+}
+
+#[typetag::serde]
+impl Filter for BoxBlur {
+    fn apply(&self, i: &mut Image, _ctx: &mut Context) -> Result<(), super::Error> {
+        let g = area(&self.area, i);
+        blur(i, &g, &box_kernel(self.radius));
+        Ok(())
+    }
+
+    fn validate(
+        &self,
+        viewbox: (u32, u32),
+        _policy: &ValidationPolicy,
+    ) -> Result<(), super::Error> {
+        if self.radius == 0 || self.radius > min(viewbox.0, viewbox.1) / 2 {
+            return Err(super::Error::invalid_filter_params(
+                "BoxBlur",
+                "radius",
+                "must be greater than 0 and at most half of the viewbox",
+            ));
+        }
+        validate_area("BoxBlur", &self.area, viewbox)
+    }
+
+    fn cost(&self, viewbox: (u32, u32)) -> u64 {
+        cost(&self.area, viewbox, 2 * self.radius as u64 + 1)
+    }
+}
@@ -1,4 +1,4 @@
-use filters::Filter;
+use filters::{Context, Filter, ValidationPolicy};
 use images::Image;
 
 #[derive(Clone, Debug, PartialEq)]
@@ -13,7 +13,7 @@ impl ColorInvert {
 
 #[typetag::serde]
 impl Filter for ColorInvert {
-    fn apply(&self, i: &mut Image) -> Result<(), super::Error> {
+    fn apply(&self, i: &mut Image, _ctx: &mut Context) -> Result<(), super::Error> {
         for y in 0..i.height() {
             for x in 0..i.width() {
                 let mut p = i.get_pixel(x, y);
@@ -25,7 +25,11 @@ impl Filter for ColorInvert {
         Ok(())
     }
 
-    fn validate(&self, _viewbox: (u32, u32)) -> Result<(), super::Error> {
+    fn validate(
+        &self,
+        _viewbox: (u32, u32),
+        _policy: &ValidationPolicy,
+    ) -> Result<(), super::Error> {
         Ok(())
     }
 }
@@ -0,0 +1,53 @@
+//! Helpers to smooth data with separable kernels.
+
+/// The smallest standard deviation of a Gaussian kernel which filters accept. Smaller ones do not
+/// change the data noticeably.
+pub const MIN_SIGMA: f32 = 0.1;
+
+/// Returns a normalized Gaussian kernel with the standard deviation `sigma`.
+///
+/// The kernel covers three standard deviations on each side of its center. If the variance is
+/// zero or not finite the kernel keeps the data unchanged.
+pub fn gaussian_kernel(sigma: f32) -> Vec<f32> {
+    let variance = sigma * sigma;
+    if variance == 0.0 || !variance.is_finite() {
+        return vec![1.0];
+    }
+    let r = (3.0 * sigma).ceil().max(1.0) as i32;
+    let k: Vec<f32> = (-r..=r)
+        .map(|x| (-(x * x) as f32 / (2.0 * variance)).exp())
+        .collect();
+    let sum: f32 = k.iter().sum();
+    k.into_iter().map(|v| v / sum).collect()
+}
+
+/// Returns a normalized kernel which averages `2 * radius + 1` values.
+pub fn box_kernel(radius: u32) -> Vec<f32> {
+    let n = 2 * radius as usize + 1;
+    vec![1.0 / n as f32; n]
+}
+
+/// Convolves the values of a `w` x `h` grid with `kernel` horizontally and then vertically.
+///
+/// Values beyond the border of the grid are replaced by the nearest value on the border.
+pub fn convolve(data: &[f32], w: usize, h: usize, kernel: &[f32]) -> Vec<f32> {
+    let r = (kernel.len() / 2) as isize;
+    let pass = |src: &[f32], step: usize, n: usize| {
+        let mut dst = vec![0.0; src.len()];
+        for (i, d) in dst.iter_mut().enumerate() {
+            // position of the value along the direction of the pass
+            let p = (i / step % n) as isize;
+            *d = kernel
+                .iter()
+                .enumerate()
+                .map(|(k, v)| {
+                    let q = (p + k as isize - r).max(0).min(n as isize - 1);
+                    v * src[(i as isize + (q - p) * step as isize) as usize]
+                })
+                .sum();
+        }
+        dst
+    };
+    let horizontal = pass(data, 1, w);
+    pass(&horizontal, w, h)
+}
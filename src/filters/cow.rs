@@ -2,7 +2,7 @@ use rand::prelude::*;
 use std::cmp::{max, min};
 use std::collections::BTreeSet;
 
-use filters::Filter;
+use filters::{Context, Filter, ValidationPolicy};
 use images::Image;
 use Geometry;
 
@@ -77,8 +77,8 @@ impl Cow {
 
 #[typetag::serde]
 impl Filter for Cow {
-    fn apply(&self, i: &mut Image) -> Result<(), super::Error> {
-        let mut rng = thread_rng();
+    fn apply(&self, i: &mut Image, ctx: &mut Context) -> Result<(), super::Error> {
+        let rng = ctx.rng();
 
         let g = match self.geometry {
             Some(ref x) => x.clone(),
@@ -92,8 +92,7 @@ impl Filter for Cow {
         let mut set = BTreeSet::new();
 
         for _ in 0..self.n {
-            let mut rng = thread_rng();
-            let p = *pixels.choose(&mut rng).ok_or("no pixels")?;
+            let p = *pixels.choose(rng).expect("pixels is never empty");
 
             let r = rng.gen_range(self.min_radius..self.max_radius + 1) as i32;
             let v = Self::get_pixels(p.0 as i32, p.1 as i32, r, i);
@@ -114,49 +113,59 @@ impl Filter for Cow {
         Ok(())
     }
 
-    fn validate(&self, viewbox: (u32, u32)) -> Result<(), super::Error> {
-        if self.min_radius <= 0
-            || self.max_radius <= 0
-            || self.min_radius >= viewbox.0
-            || self.min_radius >= viewbox.1
-            || self.max_radius >= viewbox.0
-            || self.max_radius >= viewbox.1
-        {
-            return Err("min_radius and max_radius must be greater than 0 and must be smaller than the viewbox".into());
+    fn cost(&self, viewbox: (u32, u32)) -> u64 {
+        // each circle scans its bounding box and its pixels are inverted at the end
+        let d = 2 * self.max_radius as u64 + 1;
+        let circle = min(d, viewbox.0 as u64) * min(d, viewbox.1 as u64);
+        (self.n as u64).saturating_mul(2 * circle)
+    }
+
+    fn validate(&self, viewbox: (u32, u32), policy: &ValidationPolicy) -> Result<(), super::Error> {
+        let invalid =
+            |field, reason: &str| Err(super::Error::invalid_filter_params("Cow", field, reason));
+        let size = min(viewbox.0, viewbox.1);
+
+        if self.min_radius == 0 || self.min_radius >= size {
+            return invalid(
+                "min_radius",
+                "must be greater than 0 and must be smaller than the viewbox",
+            );
+        }
+
+        if self.max_radius == 0 || self.max_radius >= size {
+            return invalid(
+                "max_radius",
+                "must be greater than 0 and must be smaller than the viewbox",
+            );
         }
 
         if self.min_radius > self.max_radius {
-            return Err("min_radius must be less than or equal to max_radius".into());
+            return invalid("min_radius", "must be less than or equal to max_radius");
         }
 
         if let Some(ref geometry) = self.geometry {
-            if geometry.top <= 0
-                || geometry.left <= 0
-                || geometry.right <= 0
-                || geometry.bottom <= 0
-            {
-                return Err("geometry top/left/right/bottom must be greater than 0".into());
-            }
-
             if geometry.left >= geometry.right {
-                return Err("geometry left must be less than geometry right".into());
+                return invalid("geometry", "left must be less than right");
             }
 
             if geometry.top >= geometry.bottom {
-                return Err("geometry top must be less than geometry bottom".into());
+                return invalid("geometry", "top must be less than bottom");
             }
 
             if geometry.right >= viewbox.0 {
-                return Err("right must be less than viewbox width".into());
+                return invalid("geometry", "right must be less than viewbox width");
             }
 
             if geometry.bottom >= viewbox.1 {
-                return Err("bottom must be less than viewbox height".into());
+                return invalid("geometry", "bottom must be less than viewbox height");
             }
         }
 
-        if self.n <= 0 || self.n >= 5 {
-            return Err("n must be greater than 0 and less than 5".into());
+        if self.n == 0 || self.n > policy.max_shapes {
+            return invalid(
+                "n",
+                &format!("must be greater than 0 and at most {}", policy.max_shapes),
+            );
         }
 
         Ok(())
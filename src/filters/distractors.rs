@@ -0,0 +1,203 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::cmp::min;
+use std::collections::hash_map::{Entry, HashMap};
+
+use super::SerdeColor;
+use filters::{Context, Filter, ValidationPolicy};
+use fonts::Font;
+use images::{Image, Pixl};
+
+/// Scatters faint, smaller and rotated glyphs of the CAPTCHA's font across the image.
+///
+/// The glyphs are drawn behind the text: a pixel only becomes darker, so that the characters of
+/// the CAPTCHA stay unchanged as long as the distractors are lighter than the text. The glyphs are
+/// not added to the characters of the CAPTCHA and do not change the area which contains text.
+/// Glyphs which are larger than the image are shrunk, so that they fit onto it in any rotation.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Distractors {
+    n: u32,
+    scale: (f32, f32),
+    max_rotation: f32,
+    color: SerdeColor,
+    alpha: f32,
+}
+
+impl Distractors {
+    pub fn new(n: u32) -> Distractors {
+        Distractors {
+            n,
+            scale: (0.4, 0.7),
+            max_rotation: 45.0,
+            color: SerdeColor::new(160, 160, 160),
+            alpha: 0.6,
+        }
+    }
+
+    /// Scales each glyph by a random factor between `min` and `max` (inclusive).
+    pub fn scale(self, min: f32, max: f32) -> Distractors {
+        Distractors {
+            scale: (min, max),
+            ..self
+        }
+    }
+
+    /// Rotates each glyph by a random angle between `-degrees` and `degrees`.
+    pub fn max_rotation(self, degrees: f32) -> Distractors {
+        Distractors {
+            max_rotation: degrees,
+            ..self
+        }
+    }
+
+    pub fn color(self, color: SerdeColor) -> Distractors {
+        Distractors { color, ..self }
+    }
+
+    /// Sets the opacity of the glyphs between 0 and 1.
+    pub fn alpha(self, alpha: f32) -> Distractors {
+        Distractors { alpha, ..self }
+    }
+
+    /// Decodes the glyph of `c` and shrinks it, so that its diagonal is at most `side` pixels.
+    fn glyph(font: &dyn Font, c: char, side: u32) -> Result<Image, super::Error> {
+        let png = font.png(c).ok_or(super::Error::UnknownChar(c))?;
+        let g = Image::from_png(png)?;
+        let diagonal = (g.width() as f32).hypot(g.height() as f32);
+        if diagonal > side as f32 {
+            Ok(g.scale(side as f32 / diagonal))
+        } else {
+            Ok(g)
+        }
+    }
+
+    /// Draws the glyph `g` with its top left corner at `x`, `y` keeping the darker value of each
+    /// channel.
+    fn draw(&self, i: &mut Image, g: &Image, x: i64, y: i64) {
+        let color = self.color.to_pixl().rgba();
+        for gy in 0..g.height() {
+            for gx in 0..g.width() {
+                let (px, py) = (x + gx as i64, y + gy as i64);
+                if px < 0 || py < 0 || px >= i.width() as i64 || py >= i.height() as i64 {
+                    continue;
+                }
+                // glyphs are black on white, so the darkness of a pixel is its coverage
+                let [r, gr, b, _] = g.get_pixel(gx, gy).rgba();
+                let coverage = 1.0 - min(r, min(gr, b)) as f32 / 255.0;
+                if coverage <= 0.0 {
+                    continue;
+                }
+                let a = self.alpha * coverage;
+                let o = i.get_pixel(px as u32, py as u32).rgba();
+                let v = |k: usize| {
+                    let mixed = (o[k] as f32 * (1.0 - a) + color[k] as f32 * a).round() as u8;
+                    min(o[k], mixed)
+                };
+                let p = Pixl::new_with_alpha(v(0), v(1), v(2), o[3]);
+                i.put_pixel(px as u32, py as u32, p);
+            }
+        }
+    }
+}
+
+#[typetag::serde]
+impl Filter for Distractors {
+    fn apply(&self, i: &mut Image, ctx: &mut Context) -> Result<(), super::Error> {
+        let font = ctx.font();
+        let chars = font.chars();
+        let rng = ctx.rng();
+        let side = min(i.width(), i.height());
+        // each glyph is only decoded once
+        let mut glyphs: HashMap<char, Image> = HashMap::new();
+        for _ in 0..self.n {
+            let c = match chars.choose(rng) {
+                Some(c) => *c,
+                None => return Ok(()),
+            };
+            let glyph = match glyphs.entry(c) {
+                Entry::Occupied(e) => e.into_mut(),
+                Entry::Vacant(e) => e.insert(Self::glyph(font, c, side)?),
+            };
+
+            let (min, max) = self.scale;
+            let f = if min < max {
+                rng.gen_range(min..=max)
+            } else {
+                min
+            };
+            let mut g = glyph.scale(f);
+            if self.max_rotation > 0.0 {
+                g = g.rotate(rng.gen_range(-self.max_rotation..=self.max_rotation));
+            }
+
+            // the center of the glyph is anywhere on the image
+            let x = rng.gen_range(0..i.width()) as i64 - g.width() as i64 / 2;
+            let y = rng.gen_range(0..i.height()) as i64 - g.height() as i64 / 2;
+            self.draw(i, &g, x, y);
+        }
+
+        Ok(())
+    }
+
+    fn cost(&self, viewbox: (u32, u32)) -> u64 {
+        // a glyph fits into a square of the smaller side of the viewbox, it is read once when it is
+        // scaled and the scaled glyph is rotated and drawn
+        let side = min(viewbox.0, viewbox.1) as f32;
+        let scaled = (side * self.scale.1.clamp(0.0, 1.0)).ceil() as u64;
+        let glyph = (side as u64).pow(2).saturating_add(3 * scaled.pow(2));
+        (self.n as u64).saturating_mul(glyph)
+    }
+
+    fn validate(
+        &self,
+        _viewbox: (u32, u32),
+        policy: &ValidationPolicy,
+    ) -> Result<(), super::Error> {
+        let invalid = |field, reason: &str| {
+            Err(super::Error::invalid_filter_params(
+                "Distractors",
+                field,
+                reason,
+            ))
+        };
+
+        if self.n == 0 || self.n > policy.max_shapes {
+            return invalid(
+                "n",
+                &format!("must be greater than 0 and at most {}", policy.max_shapes),
+            );
+        }
+
+        let (min, max) = self.scale;
+        if !(min > 0.0 && min <= max && max <= 1.0) {
+            return invalid(
+                "scale",
+                "must be an ordered range greater than 0.0 and at most 1.0",
+            );
+        }
+
+        if !(0.0..=180.0).contains(&self.max_rotation) {
+            return invalid("max_rotation", "must be between 0.0 and 180.0");
+        }
+
+        if !(self.alpha > 0.0 && self.alpha <= 1.0) {
+            return invalid("alpha", "must be greater than 0.0 and at most 1.0");
+        }
+
+        // the darkest channel decides how close a glyph comes to the black text
+        let c = &self.color;
+        let darkness = self.alpha * (255 - c.r.min(c.g).min(c.b)) as f32;
+        if darkness > policy.max_darkness as f32 {
+            return invalid(
+                "color",
+                &format!(
+                    "must be at most {} dark, alpha * (255 - darkest channel) is {}",
+                    policy.max_darkness, darkness
+                ),
+            );
+        }
+
+        Ok(())
+    }
+}
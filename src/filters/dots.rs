@@ -1,6 +1,7 @@
-use rand::{thread_rng, Rng};
+use rand::Rng;
+use std::cmp::min;
 
-use filters::Filter;
+use filters::{Context, Filter, ValidationPolicy};
 use images::{Image, Pixl};
 
 #[derive(Clone, Debug, PartialEq)]
@@ -37,8 +38,8 @@ impl Dots {
 
 #[typetag::serde]
 impl Filter for Dots {
-    fn apply(&self, i: &mut Image) -> Result<(), super::Error> {
-        let mut rng = thread_rng();
+    fn apply(&self, i: &mut Image, ctx: &mut Context) -> Result<(), super::Error> {
+        let rng = ctx.rng();
         for _ in 0..self.n {
             let x = rng.gen_range(0..i.width());
             let y = rng.gen_range(0..i.height());
@@ -49,19 +50,40 @@ impl Filter for Dots {
         Ok(())
     }
 
-    fn validate(&self, viewbox: (u32, u32)) -> Result<(), super::Error> {
-        if self.min_radius <= 0
-            || self.max_radius <= 0
-            || self.min_radius >= viewbox.0
-            || self.min_radius >= viewbox.1
-            || self.max_radius >= viewbox.0
-            || self.max_radius >= viewbox.1
-        {
-            return Err("min_radius and max_radius must be greater than 0 and must be smaller than the viewbox".into());
+    fn cost(&self, viewbox: (u32, u32)) -> u64 {
+        let d = 2 * self.max_radius as u64 + 1;
+        let circle = min(d, viewbox.0 as u64) * min(d, viewbox.1 as u64);
+        (self.n as u64).saturating_mul(circle)
+    }
+
+    fn validate(&self, viewbox: (u32, u32), policy: &ValidationPolicy) -> Result<(), super::Error> {
+        let invalid =
+            |field, reason: &str| Err(super::Error::invalid_filter_params("Dots", field, reason));
+        let size = min(viewbox.0, viewbox.1);
+
+        if self.min_radius == 0 || self.min_radius >= size {
+            return invalid(
+                "min_radius",
+                "must be greater than 0 and must be smaller than the viewbox",
+            );
+        }
+
+        if self.max_radius == 0 || self.max_radius >= size {
+            return invalid(
+                "max_radius",
+                "must be greater than 0 and must be smaller than the viewbox",
+            );
+        }
+
+        if self.min_radius > self.max_radius {
+            return invalid("min_radius", "must be less than or equal to max_radius");
         }
 
-        if self.n <= 0 || self.n >= 5 {
-            return Err("n must be greater than 0 and less than 5".into());
+        if self.n == 0 || self.n > policy.max_shapes {
+            return invalid(
+                "n",
+                &format!("must be greater than 0 and at most {}", policy.max_shapes),
+            );
         }
 
         Ok(())
@@ -0,0 +1,88 @@
+use rand::Rng;
+use std::cmp::min;
+
+use filters::convolve::{convolve, gaussian_kernel, MIN_SIGMA};
+use filters::{Context, Filter, ValidationPolicy};
+use images::{Image, Pixl};
+
+/// Distorts the image with a smooth random displacement field.
+///
+/// A random displacement is chosen for each pixel and the displacements are smoothed with a
+/// Gaussian filter. `sigma` is the standard deviation of the filter in pixels. Larger values
+/// result in smoother distortions. `alpha` is the maximum displacement of a pixel in pixels.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Elastic {
+    alpha: f32,
+    sigma: f32,
+}
+
+impl Elastic {
+    pub fn new(alpha: f32, sigma: f32) -> Elastic {
+        Elastic { alpha, sigma }
+    }
+
+    /// Returns a smoothed random field of size `w` x `h` whose largest absolute value is `alpha`.
+    fn field(&self, w: usize, h: usize, ctx: &mut Context) -> Vec<f32> {
+        let rng = ctx.rng();
+        let noise: Vec<f32> = (0..w * h).map(|_| rng.gen_range(-1.0..=1.0)).collect();
+        let field = convolve(&noise, w, h, &gaussian_kernel(self.sigma));
+        let max = field.iter().fold(0.0f32, |m, v| m.max(v.abs()));
+        if max == 0.0 {
+            return field;
+        }
+        field.into_iter().map(|v| v / max * self.alpha).collect()
+    }
+}
+
+#[typetag::serde]
+impl Filter for Elastic {
+    fn apply(&self, i: &mut Image, ctx: &mut Context) -> Result<(), super::Error> {
+        let (w, h) = (i.width(), i.height());
+        let dx = self.field(w as usize, h as usize, ctx);
+        let dy = self.field(w as usize, h as usize, ctx);
+
+        *i = i.remap(Pixl::white(), |x, y| {
+            let k = y as usize * w as usize + x as usize;
+            (x + dx[k], y + dy[k])
+        });
+        // no pixel moves farther than alpha
+        let a = self.alpha.ceil() as i64;
+        ctx.map_geometry(|g| Some(g.translate(-a, -a, w, h).union(&g.translate(a, a, w, h))));
+
+        Ok(())
+    }
+
+    fn validate(&self, viewbox: (u32, u32), policy: &ValidationPolicy) -> Result<(), super::Error> {
+        let invalid = |field, reason: &str| {
+            Err(super::Error::invalid_filter_params(
+                "Elastic", field, reason,
+            ))
+        };
+        let size = min(viewbox.0, viewbox.1) as f32;
+
+        // pixels which move farther than the viewbox only sample the background
+        let max = (policy.max_distortion as f32 * size).min(size);
+        if !self.alpha.is_finite() || self.alpha < 0.0 || self.alpha > max {
+            return invalid("alpha", &format!("must be between 0.0 and {}", max));
+        }
+
+        if self.sigma.is_nan() || self.sigma < MIN_SIGMA || self.sigma >= size {
+            return invalid(
+                "sigma",
+                &format!(
+                    "must be at least {} and must be smaller than the viewbox",
+                    MIN_SIGMA
+                ),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn cost(&self, viewbox: (u32, u32)) -> u64 {
+        // two fields are smoothed in two passes each and each pixel is interpolated from four
+        let k = 2 * (3.0 * self.sigma).ceil().max(1.0) as u64 + 1;
+        (4 * k + 6).saturating_mul(super::pixels(viewbox))
+    }
+}
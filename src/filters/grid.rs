@@ -1,5 +1,6 @@
-use filters::Filter;
+use filters::{Context, Filter, ValidationPolicy};
 use images::{Image, Pixl};
+use std::cmp::{max, min};
 
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -16,7 +17,7 @@ impl Grid {
 
 #[typetag::serde]
 impl Filter for Grid {
-    fn apply(&self, i: &mut Image) -> Result<(), super::Error> {
+    fn apply(&self, i: &mut Image, _ctx: &mut Context) -> Result<(), super::Error> {
         for y in (0..i.height()).filter(|i| i % self.y_gap == 0) {
             for x in 0..i.width() {
                 i.put_pixel(x, y, Pixl::black());
@@ -31,17 +32,35 @@ impl Filter for Grid {
         Ok(())
     }
 
-    fn validate(&self, viewbox: (u32, u32)) -> Result<(), super::Error> {
-        if self.x_gap <= 0
-            || self.y_gap <= 0
-            || self.x_gap >= viewbox.0
-            || self.x_gap >= viewbox.1
-            || self.y_gap >= viewbox.0
-            || self.y_gap >= viewbox.1
-        {
-            return Err(
-                "x_gap and y_gap must be greater than 0 and must be smaller than the viewbox"
-                    .into(),
+    fn cost(&self, viewbox: (u32, u32)) -> u64 {
+        let (w, h) = (viewbox.0 as u64, viewbox.1 as u64);
+        let rows = h / max(self.y_gap, 1) as u64 + 1;
+        let columns = w / max(self.x_gap, 1) as u64 + 1;
+        rows * w + columns * h
+    }
+
+    fn validate(&self, viewbox: (u32, u32), policy: &ValidationPolicy) -> Result<(), super::Error> {
+        let invalid =
+            |field, reason: &str| Err(super::Error::invalid_filter_params("Grid", field, reason));
+        let size = min(viewbox.0, viewbox.1);
+
+        if self.x_gap == 0 || self.x_gap < policy.min_grid_gap || self.x_gap >= size {
+            return invalid(
+                "x_gap",
+                &format!(
+                    "must be at least {} and must be smaller than the viewbox",
+                    max(policy.min_grid_gap, 1)
+                ),
+            );
+        }
+
+        if self.y_gap == 0 || self.y_gap < policy.min_grid_gap || self.y_gap >= size {
+            return invalid(
+                "y_gap",
+                &format!(
+                    "must be at least {} and must be smaller than the viewbox",
+                    max(policy.min_grid_gap, 1)
+                ),
             );
         }
 
@@ -1,5 +1,5 @@
 use super::SerdeColor;
-use filters::Filter;
+use filters::{Context, Filter, ValidationPolicy};
 use images::Image;
 
 /// Draw lines/rectangles on the screen
@@ -31,7 +31,7 @@ impl Line {
 
 #[typetag::serde]
 impl Filter for Line {
-    fn apply(&self, i: &mut Image) -> Result<(), super::Error> {
+    fn apply(&self, i: &mut Image, _ctx: &mut Context) -> Result<(), super::Error> {
         let pixl = self.color.to_pixl();
 
         // Translate pt to center of image
@@ -59,21 +59,35 @@ impl Filter for Line {
         Ok(())
     }
 
-    fn validate(&self, viewbox: (u32, u32)) -> Result<(), super::Error> {
+    fn cost(&self, _viewbox: (u32, u32)) -> u64 {
+        let (dx, dy) = (self.p2.0 - self.p1.0, self.p2.1 - self.p1.1);
+        let len = (dx * dx + dy * dy).sqrt() as u64 + 1;
+        let t = self.thickness.ceil() as u64;
+        len.saturating_mul(t.saturating_mul(t))
+    }
+
+    fn validate(
+        &self,
+        viewbox: (u32, u32),
+        _policy: &ValidationPolicy,
+    ) -> Result<(), super::Error> {
+        let invalid =
+            |field, reason: &str| Err(super::Error::invalid_filter_params("Line", field, reason));
+
         if self.thickness < 1.0 {
-            return Err("thickness must be greater than 1".into());
+            return invalid("thickness", "must be greater than 1");
         }
 
         if self.p1 == self.p2 {
-            return Err("p1 and p2 must be different".into());
+            return invalid("p2", "must be different from p1");
+        }
+
+        if self.p1.0.abs() >= viewbox.0 as f32 || self.p1.1.abs() >= viewbox.1 as f32 {
+            return invalid("p1", "must be within the viewbox");
         }
 
-        if self.p1.0.abs() >= viewbox.0 as f32
-            || self.p1.1.abs() >= viewbox.1 as f32
-            || self.p2.0.abs() >= viewbox.0 as f32
-            || self.p2.1.abs() >= viewbox.1 as f32
-        {
-            return Err("p1 and p2 must be within the viewbox".into());
+        if self.p2.0.abs() >= viewbox.0 as f32 || self.p2.1.abs() >= viewbox.1 as f32 {
+            return invalid("p2", "must be within the viewbox");
         }
 
         Ok(())
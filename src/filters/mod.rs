@@ -1,45 +1,292 @@
 //! Filters to disturb and transform CAPTCHAs.
 
+mod background;
+mod blur;
 mod color_invert;
+mod convolve;
 mod cow;
+mod distractors;
 mod dots;
+mod elastic;
 mod grid;
 mod line;
 mod noise;
+mod radial;
 mod random_line;
+mod rotate;
+mod strike_through;
+mod warp;
 mod wave;
 
+use fonts::Font;
 use images::Image;
+use rand::RngCore;
+use {Geometry, MAX_IMAGE_SIZE};
 
 // reexports
+pub use filters::background::{Background, Pattern};
+pub use filters::blur::{BoxBlur, GaussianBlur};
 pub use filters::color_invert::ColorInvert;
 pub use filters::cow::Cow;
+pub use filters::distractors::Distractors;
 pub use filters::dots::Dots;
+pub use filters::elastic::Elastic;
 pub use filters::grid::Grid;
 pub use filters::line::Line;
-pub use filters::noise::Noise;
+pub use filters::noise::{Noise, NoiseMode};
+pub use filters::radial::{Lens, Radial, Ripple, Swirl};
 pub use filters::random_line::RandomLine;
+pub use filters::rotate::Rotate;
+pub use filters::strike_through::StrikeThrough;
+pub use filters::warp::Warp;
 pub use filters::wave::Wave;
 
-pub type Error = Box<dyn std::error::Error + Send + Sync>;
+pub use Error;
+
+/// The state of a CAPTCHA which is passed to a filter in addition to the image.
+pub struct Context<'a> {
+    rng: &'a mut dyn RngCore,
+    text_area: &'a mut Geometry,
+    glyphs: &'a mut [Option<Geometry>],
+    font: &'a dyn Font,
+}
+
+impl<'a> Context<'a> {
+    pub(crate) fn new(
+        rng: &'a mut dyn RngCore,
+        text_area: &'a mut Geometry,
+        glyphs: &'a mut [Option<Geometry>],
+        font: &'a dyn Font,
+    ) -> Context<'a> {
+        Context {
+            rng,
+            text_area,
+            glyphs,
+            font,
+        }
+    }
+
+    /// Returns the random number generator of the CAPTCHA.
+    ///
+    /// Filters must draw all random numbers from this generator so that a CAPTCHA which is
+    /// created with a seeded generator can be reproduced.
+    pub fn rng(&mut self) -> &mut dyn RngCore {
+        self.rng
+    }
+
+    /// Returns the area which contains text.
+    pub fn text_area(&self) -> &Geometry {
+        self.text_area
+    }
+
+    /// Returns the font which is used for the characters of the CAPTCHA.
+    pub fn font(&self) -> &'a dyn Font {
+        self.font
+    }
+
+    /// Moves the area which contains text and the areas of the characters.
+    ///
+    /// Filters which move pixels call this method with a function which returns the area that
+    /// contains the pixels of the given area after the filter has been applied, or `None` if these
+    /// pixels have been moved off the image. Characters which have been cropped entirely lose
+    /// their area. The area which contains text keeps its previous value in that case.
+    pub fn map_geometry<F: Fn(&Geometry) -> Option<Geometry>>(&mut self, f: F) {
+        if let Some(a) = f(self.text_area) {
+            *self.text_area = a;
+        }
+        for g in self.glyphs.iter_mut() {
+            *g = g.as_ref().and_then(&f);
+        }
+    }
+}
+
+/// The limits which are checked when filters are validated.
+///
+/// Parameters which would make a filter fail or panic are rejected by every policy. A policy
+/// additionally limits parameters which are valid but result in unreadable or expensive CAPTCHAs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationPolicy {
+    /// The maximum number of shapes like dots or circles which a filter draws.
+    pub max_shapes: u32,
+    /// The maximum amplitude of a distortion relative to the smaller side of the viewbox.
+    pub max_distortion: f64,
+    /// The minimum gap in pixels between the lines of a grid.
+    pub min_grid_gap: u32,
+    /// The maximum angle in degrees by which the whole image is rotated.
+    pub max_rotation: f32,
+    /// The maximum number of characters of a CAPTCHA.
+    pub max_chars: u32,
+    /// The maximum width and height in pixels of the canvas and of the view.
+    pub max_image_size: u32,
+    /// The maximum darkness between 0 and 255 of shapes which are drawn behind the text, e.g.
+    /// 128 keeps them at least half as light as the black text.
+    pub max_darkness: u8,
+    /// The maximum number of sines of which a wave consists.
+    pub max_harmonics: u32,
+}
+
+impl ValidationPolicy {
+    /// Returns a policy for filters which have been configured by untrusted users.
+    ///
+    /// All predefined CAPTCHAs pass this policy.
+    pub fn strict() -> ValidationPolicy {
+        ValidationPolicy {
+            max_shapes: 32,
+            max_distortion: 0.25,
+            min_grid_gap: 3,
+            max_rotation: 30.0,
+            max_chars: 16,
+            max_image_size: 4096,
+            max_darkness: 128,
+            max_harmonics: 8,
+        }
+    }
+
+    /// Returns a policy which only rejects parameters that filters cannot be applied with.
+    ///
+    /// Images are still limited to 16384 pixels on each side, larger ones cannot be allocated
+    /// reliably.
+    pub fn permissive() -> ValidationPolicy {
+        ValidationPolicy {
+            max_shapes: u32::MAX,
+            max_distortion: f64::INFINITY,
+            min_grid_gap: 1,
+            max_rotation: 360.0,
+            max_chars: u32::MAX,
+            max_image_size: MAX_IMAGE_SIZE,
+            max_darkness: 255,
+            max_harmonics: u32::MAX,
+        }
+    }
+}
 
 #[typetag::serde(tag = "filter")]
 pub trait Filter: Send + Sync {
-    fn apply(&self, i: &mut Image) -> Result<(), Error>;
+    fn apply(&self, i: &mut Image, ctx: &mut Context) -> Result<(), Error>;
+
+    /// Validates that a filter is safe to call with the limits of `policy`.
+    fn validate(&self, viewbox: (u32, u32), policy: &ValidationPolicy) -> Result<(), Error>;
+
+    /// Returns an estimate of the number of pixel operations which `apply` performs on an image
+    /// of the size `viewbox`.
+    ///
+    /// The default assumes that each pixel of the image is processed once.
+    fn cost(&self, viewbox: (u32, u32)) -> u64 {
+        pixels(viewbox)
+    }
+}
+
+/// Returns the smallest area which contains the border of `g` after each of its points has been
+/// moved by `f`. The area is clipped to a canvas of size `w` x `h`.
+///
+/// Filters which move pixels with an arbitrary transformation use this function to update the
+/// area which contains text. Returns `None` if the area has been moved off the canvas entirely.
+fn map_border<F: Fn(f32, f32) -> (f32, f32)>(
+    g: &Geometry,
+    w: u32,
+    h: u32,
+    f: F,
+) -> Option<Geometry> {
+    let horizontal = (g.left..=g.right).flat_map(|x| vec![(x, g.top), (x, g.bottom)]);
+    let vertical = (g.top..=g.bottom).flat_map(|y| vec![(g.left, y), (g.right, y)]);
+    let (mut x0, mut x1, mut y0, mut y1) = (f32::MAX, f32::MIN, f32::MAX, f32::MIN);
+    for (x, y) in horizontal.chain(vertical) {
+        let (nx, ny) = f(x as f32, y as f32);
+        x0 = x0.min(nx);
+        x1 = x1.max(nx);
+        y0 = y0.min(ny);
+        y1 = y1.max(ny);
+    }
+    let (x0, x1, y0, y1) = (x0.round(), x1.round(), y0.round(), y1.round());
+    if x1 < 0.0 || y1 < 0.0 || x0 > w as f32 - 1.0 || y0 > h as f32 - 1.0 {
+        return None;
+    }
+    let clip = |v: f32, n: u32| v.max(0.0).min(n as f32 - 1.0) as u32;
+    Some(Geometry::new(
+        clip(x0, w),
+        clip(x1, w),
+        clip(y0, h),
+        clip(y1, h),
+    ))
+}
+
+/// Returns the number of pixels of an image of the size `viewbox`.
+pub(crate) fn pixels(viewbox: (u32, u32)) -> u64 {
+    viewbox.0 as u64 * viewbox.1 as u64
+}
+
+/// Returns the number of pixel operations of `Image::remap` for an image of the size `viewbox`.
+fn remap_cost(viewbox: (u32, u32)) -> u64 {
+    // each pixel is interpolated from four pixels
+    4 * pixels(viewbox)
+}
 
-    /// Validates that a filter is safe to call
-    fn validate(&self, viewbox: (u32, u32)) -> Result<(), Error>;
+/// Returns the estimated number of pixel operations of a chain of filters which are applied to an
+/// image of the size `viewbox`.
+pub fn estimate_cost(filters: &[Box<dyn Filter>], viewbox: (u32, u32)) -> u64 {
+    filters
+        .iter()
+        .fold(0, |sum: u64, f| sum.saturating_add(f.cost(viewbox)))
 }
 
 #[typetag::serde]
 impl Filter for Box<dyn Filter> {
-    fn apply(&self, i: &mut Image) -> Result<(), Error> {
-        self.as_ref().apply(i)
+    fn apply(&self, i: &mut Image, ctx: &mut Context) -> Result<(), Error> {
+        self.as_ref().apply(i, ctx)
     }
 
-    fn validate(&self, viewbox: (u32, u32)) -> Result<(), Error> {
-        self.as_ref().validate(viewbox)
+    fn validate(&self, viewbox: (u32, u32), policy: &ValidationPolicy) -> Result<(), Error> {
+        self.as_ref().validate(viewbox, policy)
     }
+
+    fn cost(&self, viewbox: (u32, u32)) -> u64 {
+        self.as_ref().cost(viewbox)
+    }
+}
+
+/// A problem with one filter of a chain of filters.
+#[derive(Debug)]
+pub struct FilterIssue {
+    /// The position of the filter within the chain.
+    pub index: usize,
+    /// The name under which the filter is serialized.
+    pub filter: &'static str,
+    /// Why the filter is invalid.
+    pub error: Error,
+}
+
+impl std::fmt::Display for FilterIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "filter #{} ({}): {}",
+            self.index, self.filter, self.error
+        )
+    }
+}
+
+/// Validates each filter of a chain for a CAPTCHA of the size `viewbox` with the limits of
+/// `policy`.
+///
+/// In contrast to stopping at the first invalid filter all problems are returned. The result is
+/// empty if all filters are valid.
+pub fn validate_filters(
+    filters: &[Box<dyn Filter>],
+    viewbox: (u32, u32),
+    policy: &ValidationPolicy,
+) -> Vec<FilterIssue> {
+    filters
+        .iter()
+        .map(|f| f.as_ref())
+        .enumerate()
+        .filter_map(|(index, f)| {
+            f.validate(viewbox, policy).err().map(|error| FilterIssue {
+                index,
+                filter: f.typetag_name(),
+                error,
+            })
+        })
+        .collect()
 }
 
 #[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -58,3 +305,217 @@ impl SerdeColor {
         crate::images::Pixl::new(self.r, self.g, self.b)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use filters::convolve::gaussian_kernel;
+    use filters::{
+        validate_filters, Background, Distractors, Dots, Elastic, Filter, GaussianBlur, Grid, Lens,
+        Noise, NoiseMode, Pattern, Radial, Ripple, Rotate, SerdeColor, Swirl, ValidationPolicy,
+        Warp, Wave,
+    };
+
+    #[test]
+    fn all_issues_are_reported() {
+        let chain: Vec<Box<dyn Filter>> = vec![
+            Box::new(Noise::new(2.0)),
+            Box::new(Wave::new(2.0, 10.0)),
+            Box::new(Grid::new(0, 8)),
+            Box::new(Dots::new(3)),
+        ];
+        let strict = ValidationPolicy::strict();
+        let issues = validate_filters(&chain, (220, 120), &strict);
+        assert_eq!(issues.len(), 2);
+        assert_eq!((issues[0].index, issues[0].filter), (0, "Noise"));
+        assert_eq!((issues[1].index, issues[1].filter), (2, "Grid"));
+        assert!(validate_filters(&chain[1..2], (220, 120), &strict).is_empty());
+    }
+
+    #[test]
+    fn policies() {
+        let chain: Vec<Box<dyn Filter>> = vec![
+            Box::new(Dots::new(100)),
+            Box::new(Wave::new(2.0, 60.0)),
+            Box::new(Grid::new(1, 1)),
+            Box::new(Rotate::random(-90.0, 90.0)),
+            Box::new(
+                Warp::perspective([(0.1, 0.0), (0.0, 0.1), (0.2, 0.0), (0.0, 0.2)]).jitter(0.2),
+            ),
+        ];
+        let strict = validate_filters(&chain, (220, 120), &ValidationPolicy::strict());
+        assert_eq!(strict.len(), 5);
+        let permissive = validate_filters(&chain, (220, 120), &ValidationPolicy::permissive());
+        assert!(permissive.is_empty());
+    }
+
+    #[test]
+    fn noise_modes() {
+        let strict = ValidationPolicy::strict();
+        let old: Box<dyn Filter> =
+            serde_json::from_str(r#"{"filter": "Noise", "prob": 0.3}"#).expect("invalid noise");
+        assert!(old.validate((220, 120), &strict).is_ok());
+
+        let gaussian = Noise::new(1.0).mode(NoiseMode::Gaussian { sigma: 300.0 });
+        assert!(gaussian.validate((220, 120), &strict).is_err());
+        let clusters = Noise::new(0.01).mode(NoiseMode::Clusters {
+            min_radius: 4,
+            max_radius: 2,
+            color: SerdeColor::new(0, 0, 0),
+        });
+        assert!(clusters.validate((220, 120), &strict).is_err());
+        let json = serde_json::to_string(&gaussian as &dyn Filter).expect("serialize failed");
+        let copy: Box<dyn Filter> = serde_json::from_str(&json).expect("deserialize failed");
+        assert_eq!(
+            serde_json::to_string(&copy).expect("serialize failed"),
+            json
+        );
+    }
+
+    #[test]
+    fn background_limits() {
+        let strict = ValidationPolicy::strict();
+        let (a, b) = (
+            SerdeColor::new(200, 220, 255),
+            SerdeColor::new(255, 240, 200),
+        );
+        let stripes = |width| Pattern::Stripes {
+            a: a.clone(),
+            b: b.clone(),
+            width,
+            angle: 20.0,
+        };
+        let noise = |scale, octaves| Pattern::Noise {
+            from: a.clone(),
+            to: b.clone(),
+            scale,
+            octaves,
+        };
+        let valid = |p| Background::new(p).validate((220, 120), &strict).is_ok();
+
+        assert!(valid(stripes(4)));
+        assert!(!valid(Pattern::Checkerboard {
+            a: a.clone(),
+            b: SerdeColor::new(0, 0, 0),
+            size: 4,
+        }));
+        assert!(!valid(stripes(0)));
+        let dark = Pattern::Checkerboard {
+            a: SerdeColor::new(20, 20, 20),
+            b: b.clone(),
+            size: 4,
+        };
+        assert!(!valid(dark.clone()));
+        assert!(Background::new(dark)
+            .validate((220, 120), &ValidationPolicy::permissive())
+            .is_ok());
+        assert!(valid(noise(30.0, 4)));
+        assert!(!valid(noise(0.5, 4)));
+        assert!(!valid(noise(30.0, 0)));
+        assert!(!valid(Pattern::RadialGradient {
+            inner: a.clone(),
+            outer: b.clone(),
+            center: (1.5, 0.5),
+        }));
+        assert!(Background::new(noise(30.0, 4)).cost((220, 120)) > 220 * 120);
+    }
+
+    #[test]
+    fn radial_limits() {
+        let strict = ValidationPolicy::strict();
+        let permissive = ValidationPolicy::permissive();
+        let swirl = Swirl::new(Radial::new(90.0).radius(0.4));
+        assert!(swirl.validate((220, 120), &strict).is_ok());
+        let swirl = Swirl::new(Radial::new(360.0).center_range((0.2, 0.8), (0.4, 0.6)));
+        assert!(swirl.validate((220, 120), &strict).is_err());
+        assert!(swirl.validate((220, 120), &permissive).is_ok());
+        assert!(Lens::new(Radial::new(0.5))
+            .validate((220, 120), &strict)
+            .is_ok());
+        assert!(Lens::new(Radial::new(-0.8))
+            .validate((220, 120), &permissive)
+            .is_err());
+        let ripple = Ripple::new(Radial::new(3.0).radius_range(0.3, 0.6), 12.0);
+        assert!(ripple.validate((220, 120), &strict).is_ok());
+        assert!(Ripple::new(Radial::new(3.0), 1.0)
+            .validate((220, 120), &permissive)
+            .is_err());
+        for (strength, wavelength) in [(1e20, 1e30), (3.0, 121.0), (f32::INFINITY, 10.0)] {
+            assert!(Ripple::new(Radial::new(strength), wavelength)
+                .validate((220, 120), &permissive)
+                .is_err());
+        }
+        let center = Radial::new(1.0).center(1.5, 0.5);
+        assert!(Lens::new(center).validate((220, 120), &permissive).is_err());
+    }
+
+    #[test]
+    fn wave_harmonics() {
+        let wave = (0..20).fold(Wave::new(2.0, 1.0), |w, k| w.harmonic(k as f64, 0.0));
+        assert!(wave
+            .validate((220, 120), &ValidationPolicy::strict())
+            .is_err());
+        assert!(wave
+            .validate((220, 120), &ValidationPolicy::permissive())
+            .is_ok());
+        let single = Wave::new(2.0, 1.0).cost((220, 120));
+        assert_eq!(wave.cost((220, 120)), single + 20 * (220 + 120));
+    }
+
+    #[test]
+    fn blur_limits() {
+        let strict = ValidationPolicy::strict();
+        assert!(GaussianBlur::new(0.1).validate((220, 120), &strict).is_ok());
+        for sigma in [0.0, 1e-30, 0.09, 61.0, f32::NAN] {
+            assert!(GaussianBlur::new(sigma)
+                .validate((220, 120), &strict)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn elastic_limits() {
+        let permissive = ValidationPolicy::permissive();
+        assert!(Elastic::new(100.0, 8.0)
+            .validate((220, 120), &permissive)
+            .is_ok());
+        for alpha in [121.0, 1e30, f32::INFINITY, f32::NAN] {
+            assert!(Elastic::new(alpha, 8.0)
+                .validate((220, 120), &permissive)
+                .is_err());
+        }
+        assert!(Elastic::new(5.0, 1e-30)
+            .validate((220, 120), &ValidationPolicy::strict())
+            .is_err());
+        assert_eq!(gaussian_kernel(1e-30), vec![1.0]);
+        assert_eq!(gaussian_kernel(f32::INFINITY), vec![1.0]);
+    }
+
+    #[test]
+    fn distractors_stay_light() {
+        let strict = ValidationPolicy::strict();
+        let permissive = ValidationPolicy::permissive();
+        assert!(Distractors::new(8).validate((220, 120), &strict).is_ok());
+        let black = Distractors::new(8)
+            .color(SerdeColor::new(0, 0, 0))
+            .alpha(1.0);
+        assert!(black.validate((220, 120), &strict).is_err());
+        assert!(black.validate((220, 120), &permissive).is_ok());
+        // a faint black is as light as an opaque gray
+        let faint = Distractors::new(8)
+            .color(SerdeColor::new(0, 0, 0))
+            .alpha(0.4);
+        assert!(faint.validate((220, 120), &strict).is_ok());
+    }
+
+    #[test]
+    fn warp_stays_on_canvas() {
+        let strict = ValidationPolicy::strict();
+        let off_canvas = Warp::perspective([(-0.1, 0.0), (0.0, 0.0), (0.0, 0.0), (0.0, 0.0)]);
+        assert!(off_canvas.validate((220, 120), &strict).is_err());
+        // the bottom right corner of this parallelogram is outside of the image
+        let sheared = Warp::affine([(0.0, 0.0), (0.0, 0.0), (0.2, 0.0)]);
+        assert!(sheared.validate((220, 120), &strict).is_err());
+        let sheared = Warp::affine([(0.2, 0.0), (0.0, 0.0), (0.0, 0.0)]);
+        assert!(sheared.validate((220, 120), &strict).is_ok());
+    }
+}
@@ -1,28 +1,113 @@
-use rand::{thread_rng, Rng};
+use rand::{Rng, RngCore};
+use std::cmp::min;
 
-use filters::Filter;
+use super::SerdeColor;
+use filters::{Context, Filter, ValidationPolicy};
 use images::{Image, Pixl};
 
+/// Determines how a pixel which is hit by noise is changed.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NoiseMode {
+    /// The pixel becomes black.
+    #[default]
+    Pepper,
+    /// A random value from a normal distribution with the standard deviation `sigma` is added to
+    /// the intensity of the pixel.
+    Gaussian { sigma: f32 },
+    /// The pixel gets the color `salt` or `pepper` with equal probability.
+    SaltAndPepper {
+        salt: SerdeColor,
+        pepper: SerdeColor,
+    },
+    /// The pixel gets a fully saturated color of a random hue.
+    RandomHue,
+    /// A filled circle of `color` with a random radius between `min_radius` and `max_radius`
+    /// (inclusive) is drawn around the pixel.
+    Clusters {
+        min_radius: u32,
+        max_radius: u32,
+        color: SerdeColor,
+    },
+}
+
+/// Changes each pixel with the probability `prob` as determined by the mode of the noise.
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Noise {
     prob: f32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    mode: NoiseMode,
 }
 
 impl Noise {
     pub fn new(prob: f32) -> Noise {
-        Noise { prob }
+        Noise {
+            prob,
+            mode: NoiseMode::Pepper,
+        }
+    }
+
+    pub fn mode(self, mode: NoiseMode) -> Noise {
+        Noise { mode, ..self }
+    }
+
+    /// Returns a random value from the standard normal distribution.
+    fn normal(rng: &mut dyn RngCore) -> f32 {
+        // Box-Muller transform
+        let u: f32 = 1.0 - rng.gen::<f32>();
+        let v: f32 = rng.gen();
+        (-2.0 * u.ln()).sqrt() * (std::f32::consts::TAU * v).cos()
+    }
+
+    /// Returns a fully saturated color of a random hue.
+    fn random_hue(rng: &mut dyn RngCore) -> Pixl {
+        let h = rng.gen_range(0.0..6.0f32);
+        let x = ((1.0 - (h % 2.0 - 1.0).abs()) * 255.0).round() as u8;
+        match h as u32 {
+            0 => Pixl::new(255, x, 0),
+            1 => Pixl::new(x, 255, 0),
+            2 => Pixl::new(0, 255, x),
+            3 => Pixl::new(0, x, 255),
+            4 => Pixl::new(x, 0, 255),
+            _ => Pixl::new(255, 0, x),
+        }
     }
 }
 
 #[typetag::serde]
 impl Filter for Noise {
-    fn apply(&self, i: &mut Image) -> Result<(), super::Error> {
-        let mut rng = thread_rng();
+    fn apply(&self, i: &mut Image, ctx: &mut Context) -> Result<(), super::Error> {
+        let rng = ctx.rng();
         for y in 0..i.height() {
             for x in 0..i.width() {
-                if rng.gen::<f32>() <= self.prob {
-                    i.put_pixel(x, y, Pixl::black());
+                if rng.gen::<f32>() > self.prob {
+                    continue;
+                }
+                match self.mode {
+                    NoiseMode::Pepper => i.put_pixel(x, y, Pixl::black()),
+                    NoiseMode::Gaussian { sigma } => {
+                        let n = Self::normal(rng) * sigma;
+                        let [r, g, b, a] = i.get_pixel(x, y).rgba();
+                        let v = |c: u8| (c as f32 + n).round().clamp(0.0, 255.0) as u8;
+                        i.put_pixel(x, y, Pixl::new_with_alpha(v(r), v(g), v(b), a));
+                    }
+                    NoiseMode::SaltAndPepper {
+                        ref salt,
+                        ref pepper,
+                    } => {
+                        let c = if rng.gen() { salt } else { pepper };
+                        i.put_pixel(x, y, c.to_pixl());
+                    }
+                    NoiseMode::RandomHue => i.put_pixel(x, y, Self::random_hue(rng)),
+                    NoiseMode::Clusters {
+                        min_radius,
+                        max_radius,
+                        ref color,
+                    } => {
+                        let r = rng.gen_range(min_radius..=max_radius);
+                        i.fill_circle(x, y, r, color.to_pixl());
+                    }
                 }
             }
         }
@@ -30,11 +115,44 @@ impl Filter for Noise {
         Ok(())
     }
 
-    fn validate(&self, _viewbox: (u32, u32)) -> Result<(), super::Error> {
-        if self.prob < 0.0 || self.prob > 1.0 {
-            return Err("prob must be between 0.0 and 1.0".into());
+    fn validate(
+        &self,
+        viewbox: (u32, u32),
+        _policy: &ValidationPolicy,
+    ) -> Result<(), super::Error> {
+        let invalid =
+            |field, reason: &str| Err(super::Error::invalid_filter_params("Noise", field, reason));
+
+        if self.prob.is_nan() || self.prob < 0.0 || self.prob > 1.0 {
+            return invalid("prob", "must be between 0.0 and 1.0");
         }
 
-        Ok(())
+        match self.mode {
+            NoiseMode::Gaussian { sigma } if !(0.0..=255.0).contains(&sigma) => {
+                invalid("sigma", "must be between 0.0 and 255.0")
+            }
+            NoiseMode::Clusters {
+                min_radius,
+                max_radius,
+                ..
+            } if min_radius > max_radius || max_radius >= min(viewbox.0, viewbox.1) => invalid(
+                "max_radius",
+                "must not be less than min_radius and must be smaller than the viewbox",
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    fn cost(&self, viewbox: (u32, u32)) -> u64 {
+        let pixels = super::pixels(viewbox);
+        match self.mode {
+            NoiseMode::Clusters { max_radius, .. } => {
+                // each cluster which is expected to be drawn scans the bounding box of its circle
+                let d = 2 * max_radius as u64 + 1;
+                let clusters = (pixels as f64 * self.prob.clamp(0.0, 1.0) as f64).ceil() as u64;
+                pixels.saturating_add(clusters.saturating_mul(d * d))
+            }
+            _ => pixels,
+        }
     }
 }
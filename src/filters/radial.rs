@@ -0,0 +1,330 @@
+use rand::{Rng, RngCore};
+use std::cmp::{max, min};
+use std::f32::consts::PI;
+
+use filters::{Context, Filter, ValidationPolicy};
+use images::{Image, Pixl};
+
+/// The area and the strength of a radial distortion.
+///
+/// The center is given as fraction of the width and the height of the image and the radius as
+/// fraction of its smaller side. Pixels outside of the radius are not moved. Each parameter is a
+/// range from which a random value is chosen whenever the distortion is applied.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Radial {
+    center_x: (f32, f32),
+    center_y: (f32, f32),
+    radius: (f32, f32),
+    strength: (f32, f32),
+}
+
+impl Radial {
+    /// Returns a distortion of the given strength in the center of the image whose radius is
+    /// half of the smaller side of the image.
+    pub fn new(strength: f32) -> Radial {
+        Radial {
+            center_x: (0.5, 0.5),
+            center_y: (0.5, 0.5),
+            radius: (0.5, 0.5),
+            strength: (strength, strength),
+        }
+    }
+
+    pub fn strength_range(self, min: f32, max: f32) -> Radial {
+        Radial {
+            strength: (min, max),
+            ..self
+        }
+    }
+
+    pub fn center(self, x: f32, y: f32) -> Radial {
+        self.center_range((x, x), (y, y))
+    }
+
+    /// Places the center at a random position within the given ranges.
+    pub fn center_range(self, x: (f32, f32), y: (f32, f32)) -> Radial {
+        Radial {
+            center_x: x,
+            center_y: y,
+            ..self
+        }
+    }
+
+    pub fn radius(self, r: f32) -> Radial {
+        self.radius_range(r, r)
+    }
+
+    pub fn radius_range(self, min: f32, max: f32) -> Radial {
+        Radial {
+            radius: (min, max),
+            ..self
+        }
+    }
+
+    /// Returns the center and the radius in pixels and the strength for an image of size `w` x
+    /// `h`.
+    fn choose(&self, rng: &mut dyn RngCore, w: u32, h: u32) -> ((f32, f32), f32, f32) {
+        let mut pick = |(min, max): (f32, f32)| {
+            if min < max {
+                rng.gen_range(min..=max)
+            } else {
+                min
+            }
+        };
+        let cx = pick(self.center_x) * (w as f32 - 1.0);
+        let cy = pick(self.center_y) * (h as f32 - 1.0);
+        let r = pick(self.radius) * min(w, h) as f32;
+        ((cx, cy), r.max(1.0), pick(self.strength))
+    }
+
+    /// Moves the pixels within the radius to the positions returned by `f`.
+    ///
+    /// `f` is called with the offset of a pixel from the center and its distance to the center
+    /// relative to the radius, which is less than 1, and returns the offset from the center of
+    /// the position whose color the pixel gets.
+    fn remap<F: Fn(f32, f32, f32) -> (f32, f32)>(
+        i: &mut Image,
+        (cx, cy): (f32, f32),
+        r: f32,
+        f: F,
+    ) {
+        *i = i.remap(Pixl::white(), |x, y| {
+            let (dx, dy) = (x - cx, y - cy);
+            let d = (dx * dx + dy * dy).sqrt() / r;
+            if d >= 1.0 {
+                return (x, y);
+            }
+            let (sx, sy) = f(dx, dy, d);
+            (cx + sx, cy + sy)
+        });
+    }
+
+    /// Enlarges the areas of the context by the largest number of pixels by which a pixel is
+    /// moved.
+    fn map_geometry(ctx: &mut Context, displacement: f32, w: u32, h: u32) {
+        // pixels cannot move farther than across the whole image
+        let a = displacement.ceil().clamp(0.0, max(w, h) as f32) as i64;
+        ctx.map_geometry(|g| Some(g.translate(-a, -a, w, h).union(&g.translate(a, a, w, h))));
+    }
+
+    /// Validates the parameters for a distortion which moves pixels by at most
+    /// `displacement(radius, strength)` pixels.
+    fn validate<F: Fn(f32, f32) -> f32>(
+        &self,
+        filter: &'static str,
+        viewbox: (u32, u32),
+        policy: &ValidationPolicy,
+        (min_strength, max_strength): (f32, f32),
+        displacement: F,
+    ) -> Result<(), super::Error> {
+        let invalid =
+            |field, reason: &str| Err(super::Error::invalid_filter_params(filter, field, reason));
+        let ordered = |(min, max): (f32, f32)| min <= max;
+        let within = |(min, max): (f32, f32), lo: f32, hi: f32| min >= lo && max <= hi;
+
+        if !ordered(self.center_x) || !within(self.center_x, 0.0, 1.0) {
+            return invalid("center_x", "must be an ordered range between 0.0 and 1.0");
+        }
+
+        if !ordered(self.center_y) || !within(self.center_y, 0.0, 1.0) {
+            return invalid("center_y", "must be an ordered range between 0.0 and 1.0");
+        }
+
+        if !ordered(self.radius) || !within(self.radius, f32::MIN_POSITIVE, 1.0) {
+            return invalid(
+                "radius",
+                "must be an ordered range greater than 0.0 and at most 1.0",
+            );
+        }
+
+        if !ordered(self.strength) || !within(self.strength, min_strength, max_strength) {
+            return invalid(
+                "strength",
+                &format!(
+                    "must be an ordered range between {} and {}",
+                    min_strength, max_strength
+                ),
+            );
+        }
+
+        let size = min(viewbox.0, viewbox.1) as f32;
+        let r = self.radius.1 * size;
+        let d = displacement(r, self.strength.0).max(displacement(r, self.strength.1));
+        // pixels which move farther than the viewbox only sample the background
+        let max = (policy.max_distortion as f32 * size).min(size);
+        if d.is_nan() || d > max {
+            return invalid(
+                "strength",
+                &format!("must not move pixels by more than {} pixels", max),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Twists the image around a center.
+///
+/// The strength is the angle in degrees by which the center is rotated. The angle decreases
+/// towards the radius.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Swirl {
+    radial: Radial,
+}
+
+impl Swirl {
+    pub fn new(radial: Radial) -> Swirl {
+        Swirl { radial }
+    }
+
+    /// Returns the angle in radians by which a pixel at the relative distance `d` is rotated.
+    fn angle(strength: f32, d: f32) -> f32 {
+        strength.to_radians() * (1.0 - d) * (1.0 - d)
+    }
+
+    fn displacement(r: f32, strength: f32) -> f32 {
+        (0..=100)
+            .map(|k| {
+                let d = k as f32 / 100.0;
+                2.0 * d * r * (Self::angle(strength, d) / 2.0).sin().abs()
+            })
+            .fold(0.0, f32::max)
+    }
+}
+
+#[typetag::serde]
+impl Filter for Swirl {
+    fn apply(&self, i: &mut Image, ctx: &mut Context) -> Result<(), super::Error> {
+        let (w, h) = (i.width(), i.height());
+        let (center, r, strength) = self.radial.choose(ctx.rng(), w, h);
+        Radial::remap(i, center, r, |dx, dy, d| {
+            let (s, c) = Self::angle(strength, d).sin_cos();
+            (c * dx - s * dy, s * dx + c * dy)
+        });
+        Radial::map_geometry(ctx, Self::displacement(r, strength), w, h);
+
+        Ok(())
+    }
+
+    fn validate(&self, viewbox: (u32, u32), policy: &ValidationPolicy) -> Result<(), super::Error> {
+        self.radial.validate(
+            "Swirl",
+            viewbox,
+            policy,
+            (-720.0, 720.0),
+            Self::displacement,
+        )
+    }
+
+    fn cost(&self, viewbox: (u32, u32)) -> u64 {
+        super::remap_cost(viewbox)
+    }
+}
+
+/// Moves pixels towards or away from a center along a sine wave, like ripples on water.
+///
+/// The strength is the amplitude of the wave in pixels. The amplitude decreases towards the
+/// radius.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ripple {
+    radial: Radial,
+    wavelength: f32,
+}
+
+impl Ripple {
+    /// Returns ripples whose crests are `wavelength` pixels apart.
+    pub fn new(radial: Radial, wavelength: f32) -> Ripple {
+        Ripple { radial, wavelength }
+    }
+
+    fn displacement(_r: f32, strength: f32) -> f32 {
+        strength.abs()
+    }
+}
+
+#[typetag::serde]
+impl Filter for Ripple {
+    fn apply(&self, i: &mut Image, ctx: &mut Context) -> Result<(), super::Error> {
+        let (w, h) = (i.width(), i.height());
+        let (center, r, strength) = self.radial.choose(ctx.rng(), w, h);
+        Radial::remap(i, center, r, |dx, dy, d| {
+            let t = 2.0 * PI * d * r / self.wavelength;
+            let f = 1.0 + strength * t.sin() * (1.0 - d) / (d * r).max(1.0);
+            (dx * f, dy * f)
+        });
+        Radial::map_geometry(ctx, Self::displacement(r, strength), w, h);
+
+        Ok(())
+    }
+
+    fn validate(&self, viewbox: (u32, u32), policy: &ValidationPolicy) -> Result<(), super::Error> {
+        let size = min(viewbox.0, viewbox.1) as f32;
+        if self.wavelength.is_nan() || self.wavelength < 2.0 || self.wavelength > size.max(2.0) {
+            return Err(super::Error::invalid_filter_params(
+                "Ripple",
+                "wavelength",
+                "must be at least 2.0 and must not be larger than the viewbox",
+            ));
+        }
+        self.radial.validate(
+            "Ripple",
+            viewbox,
+            policy,
+            (-self.wavelength / 2.0, self.wavelength / 2.0),
+            Self::displacement,
+        )
+    }
+
+    fn cost(&self, viewbox: (u32, u32)) -> u64 {
+        super::remap_cost(viewbox)
+    }
+}
+
+/// Simulates the distortion of a lens.
+///
+/// A positive strength magnifies the center like a barrel distortion, a negative strength
+/// shrinks it like a pincushion distortion.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Lens {
+    radial: Radial,
+}
+
+impl Lens {
+    pub fn new(radial: Radial) -> Lens {
+        Lens { radial }
+    }
+
+    fn displacement(r: f32, strength: f32) -> f32 {
+        // the largest displacement is at 1 / sqrt(3) of the radius
+        r * strength.abs() * 2.0 / (3.0 * 3f32.sqrt())
+    }
+}
+
+#[typetag::serde]
+impl Filter for Lens {
+    fn apply(&self, i: &mut Image, ctx: &mut Context) -> Result<(), super::Error> {
+        let (w, h) = (i.width(), i.height());
+        let (center, r, strength) = self.radial.choose(ctx.rng(), w, h);
+        Radial::remap(i, center, r, |dx, dy, d| {
+            let f = 1.0 - strength * (1.0 - d * d);
+            (dx * f, dy * f)
+        });
+        Radial::map_geometry(ctx, Self::displacement(r, strength), w, h);
+
+        Ok(())
+    }
+
+    fn validate(&self, viewbox: (u32, u32), policy: &ValidationPolicy) -> Result<(), super::Error> {
+        // outside of this range pixels would be folded over each other
+        self.radial
+            .validate("Lens", viewbox, policy, (-0.45, 0.9), Self::displacement)
+    }
+
+    fn cost(&self, viewbox: (u32, u32)) -> u64 {
+        super::remap_cost(viewbox)
+    }
+}
@@ -1,8 +1,7 @@
-use filters::Filter;
+use filters::{Context, Filter, ValidationPolicy};
 use image::Rgba;
 use images::Image;
-use rand::thread_rng;
-use rand::{rngs::ThreadRng, Rng};
+use rand::{Rng, RngCore};
 
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -13,7 +12,7 @@ impl RandomLine {
         RandomLine {}
     }
 
-    fn gen_line_color(rng: &mut ThreadRng) -> Rgba<u8> {
+    fn gen_line_color(rng: &mut dyn RngCore) -> Rgba<u8> {
         let red = rng.gen_range(100..=255);
         let green = rng.gen_range(100..=255);
         let blue = rng.gen_range(100..=255);
@@ -23,10 +22,10 @@ impl RandomLine {
 
 #[typetag::serde]
 impl Filter for RandomLine {
-    fn apply(&self, img: &mut Image) -> Result<(), super::Error> {
-        let mut rng = thread_rng();
+    fn apply(&self, img: &mut Image, ctx: &mut Context) -> Result<(), super::Error> {
+        let rng = ctx.rng();
 
-        let line_color = Self::gen_line_color(&mut rng);
+        let line_color = Self::gen_line_color(rng);
         let is_h = rng.gen();
         let (start, end) = if is_h {
             let xa = rng.gen_range(0.0..(img.width() as f32) / 2.0);
@@ -62,7 +61,16 @@ impl Filter for RandomLine {
         Ok(())
     }
 
-    fn validate(&self, _viewbox: (u32, u32)) -> Result<(), super::Error> {
+    fn cost(&self, viewbox: (u32, u32)) -> u64 {
+        // at most three parallel lines which are not longer than the width plus the height
+        3 * (viewbox.0 as u64 + viewbox.1 as u64)
+    }
+
+    fn validate(
+        &self,
+        _viewbox: (u32, u32),
+        _policy: &ValidationPolicy,
+    ) -> Result<(), super::Error> {
         Ok(())
     }
 }
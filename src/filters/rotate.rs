@@ -0,0 +1,98 @@
+use rand::Rng;
+
+use super::SerdeColor;
+use filters::{map_border, Context, Filter, ValidationPolicy};
+use images::Image;
+
+/// Rotates the whole image around its center.
+///
+/// The angle is given in degrees and positive angles rotate clockwise. If a range of angles is
+/// given the image is rotated by an angle which is randomly chosen from that range. Areas which
+/// are not covered by the rotated image are filled with the background color.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rotate {
+    min_angle: f32,
+    max_angle: f32,
+    background: SerdeColor,
+}
+
+impl Rotate {
+    /// Rotates the image by `degrees`.
+    pub fn new(degrees: f32) -> Rotate {
+        Rotate {
+            min_angle: degrees,
+            max_angle: degrees,
+            background: SerdeColor::new(255, 255, 255),
+        }
+    }
+
+    /// Rotates the image by a random angle between `min` and `max` (inclusive).
+    pub fn random(min: f32, max: f32) -> Rotate {
+        Rotate {
+            min_angle: min,
+            max_angle: max,
+            ..Rotate::new(0.0)
+        }
+    }
+
+    pub fn background(self, background: SerdeColor) -> Rotate {
+        Rotate { background, ..self }
+    }
+}
+
+#[typetag::serde]
+impl Filter for Rotate {
+    fn apply(&self, i: &mut Image, ctx: &mut Context) -> Result<(), super::Error> {
+        let degrees = if self.min_angle < self.max_angle {
+            ctx.rng().gen_range(self.min_angle..=self.max_angle)
+        } else {
+            self.min_angle
+        };
+        let (s, c) = degrees.to_radians().sin_cos();
+        let (w, h) = (i.width(), i.height());
+        let cx = (w as f32 - 1.0) / 2.0;
+        let cy = (h as f32 - 1.0) / 2.0;
+
+        *i = i.remap(self.background.to_pixl(), |x, y| {
+            let (dx, dy) = (x - cx, y - cy);
+            (cx + c * dx + s * dy, cy - s * dx + c * dy)
+        });
+        ctx.map_geometry(|g| {
+            map_border(g, w, h, |x, y| {
+                let (dx, dy) = (x - cx, y - cy);
+                (cx + c * dx - s * dy, cy + s * dx + c * dy)
+            })
+        });
+
+        Ok(())
+    }
+
+    fn validate(
+        &self,
+        _viewbox: (u32, u32),
+        policy: &ValidationPolicy,
+    ) -> Result<(), super::Error> {
+        let invalid =
+            |field, reason: &str| Err(super::Error::invalid_filter_params("Rotate", field, reason));
+
+        for (field, angle) in &[("min_angle", self.min_angle), ("max_angle", self.max_angle)] {
+            if angle.is_nan() || angle.abs() > policy.max_rotation {
+                return invalid(
+                    field,
+                    &format!("must be between -{0} and {0}", policy.max_rotation),
+                );
+            }
+        }
+
+        if self.min_angle > self.max_angle {
+            return invalid("min_angle", "must be less than or equal to max_angle");
+        }
+
+        Ok(())
+    }
+
+    fn cost(&self, viewbox: (u32, u32)) -> u64 {
+        super::remap_cost(viewbox)
+    }
+}
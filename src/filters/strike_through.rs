@@ -0,0 +1,160 @@
+use rand::{Rng, RngCore};
+use std::cmp::min;
+
+use super::SerdeColor;
+use filters::{Context, Filter, ValidationPolicy};
+use images::Image;
+use Geometry;
+
+/// Draws thick cubic Bezier curves through the text.
+///
+/// The curves start near the left and end near the right edge of the area which contains text.
+/// Their control points are chosen randomly within that area, so that each curve crosses the
+/// characters.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StrikeThrough {
+    n: u32,
+    thickness: f32,
+    color: SerdeColor,
+    antialias: bool,
+}
+
+impl StrikeThrough {
+    pub fn new(n: u32) -> StrikeThrough {
+        StrikeThrough {
+            n,
+            thickness: 3.0,
+            color: SerdeColor::new(0, 0, 0),
+            antialias: false,
+        }
+    }
+
+    pub fn thickness(self, thickness: f32) -> StrikeThrough {
+        StrikeThrough { thickness, ..self }
+    }
+
+    pub fn color(self, color: SerdeColor) -> StrikeThrough {
+        StrikeThrough { color, ..self }
+    }
+
+    /// Smooths the edges of the curves.
+    pub fn antialias(self, antialias: bool) -> StrikeThrough {
+        StrikeThrough { antialias, ..self }
+    }
+
+    /// Returns four random control points of a curve through the area `g`.
+    fn control_points(rng: &mut dyn RngCore, g: &Geometry) -> [(f32, f32); 4] {
+        let (l, r) = (g.left as f32, g.right as f32 + 1.0);
+        let (t, b) = (g.top as f32, g.bottom as f32 + 1.0);
+        let q = (r - l) / 4.0;
+        let mut y = || rng.gen_range(t..b);
+        let (y0, y1, y2, y3) = (y(), y(), y(), y());
+        [
+            (rng.gen_range(l..l + q), y0),
+            (rng.gen_range(l..r), y1),
+            (rng.gen_range(l..r), y2),
+            (rng.gen_range(r - q..r), y3),
+        ]
+    }
+
+    /// Adds the coverage of a curve with the control points `p` to `coverage`.
+    fn cover(&self, p: &[(f32, f32); 4], coverage: &mut [f32], w: u32, h: u32) {
+        let len: f32 = p
+            .windows(2)
+            .map(|s| ((s[1].0 - s[0].0).powi(2) + (s[1].1 - s[0].1).powi(2)).sqrt())
+            .sum();
+        // consecutive points are at most half a pixel apart
+        let steps = (2.0 * len).ceil().max(1.0) as u32;
+        let r = self.thickness / 2.0;
+        let reach = r.ceil() as i64 + 1;
+
+        for k in 0..=steps {
+            let t = k as f32 / steps as f32;
+            let u = 1.0 - t;
+            let weights = [u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t];
+            let cx: f32 = p.iter().zip(&weights).map(|(q, w)| q.0 * w).sum();
+            let cy: f32 = p.iter().zip(&weights).map(|(q, w)| q.1 * w).sum();
+
+            for y in cy as i64 - reach..=cy as i64 + reach {
+                for x in cx as i64 - reach..=cx as i64 + reach {
+                    if x < 0 || y < 0 || x >= w as i64 || y >= h as i64 {
+                        continue;
+                    }
+                    let d = ((x as f32 + 0.5 - cx).powi(2) + (y as f32 + 0.5 - cy).powi(2)).sqrt();
+                    let c = if self.antialias {
+                        (r + 0.5 - d).clamp(0.0, 1.0)
+                    } else if d <= r {
+                        1.0
+                    } else {
+                        0.0
+                    };
+                    let i = (y * w as i64 + x) as usize;
+                    coverage[i] = coverage[i].max(c);
+                }
+            }
+        }
+    }
+}
+
+#[typetag::serde]
+impl Filter for StrikeThrough {
+    fn apply(&self, i: &mut Image, ctx: &mut Context) -> Result<(), super::Error> {
+        let (w, h) = (i.width(), i.height());
+        let g = ctx.text_area().clone();
+        let mut coverage = vec![0.0; (w * h) as usize];
+        for _ in 0..self.n {
+            let p = Self::control_points(ctx.rng(), &g);
+            self.cover(&p, &mut coverage, w, h);
+        }
+
+        let pixl = self.color.to_pixl();
+        for y in 0..h {
+            for x in 0..w {
+                let c = coverage[(y * w + x) as usize];
+                if c > 0.0 {
+                    i.blend_pixel(x, y, pixl, c);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate(&self, viewbox: (u32, u32), policy: &ValidationPolicy) -> Result<(), super::Error> {
+        let invalid = |field, reason: &str| {
+            Err(super::Error::invalid_filter_params(
+                "StrikeThrough",
+                field,
+                reason,
+            ))
+        };
+        let max = min(viewbox.0, viewbox.1) as f32 / 4.0;
+
+        if self.n == 0 || self.n > policy.max_shapes {
+            return invalid(
+                "n",
+                &format!("must be greater than 0 and at most {}", policy.max_shapes),
+            );
+        }
+
+        if self.thickness.is_nan() || self.thickness < 1.0 || self.thickness > max {
+            return invalid(
+                "thickness",
+                "must be at least 1 and at most a quarter of the viewbox",
+            );
+        }
+
+        Ok(())
+    }
+
+    fn cost(&self, viewbox: (u32, u32)) -> u64 {
+        // a curve is not longer than three times the width and the height and each of its points
+        // covers a square of the size of the thickness
+        let points = 6 * (viewbox.0 as u64 + viewbox.1 as u64);
+        let t = self.thickness.max(0.0).min(u16::MAX as f32) as u64 + 3;
+        (self.n as u64)
+            .saturating_mul(points * t * t)
+            .saturating_add(super::pixels(viewbox))
+    }
+}
@@ -0,0 +1,169 @@
+use imageproc::geometric_transformations::Projection;
+use rand::Rng;
+
+use super::SerdeColor;
+use filters::{map_border, Context, Filter, ValidationPolicy};
+use images::Image;
+
+/// The kind of transformation of a `Warp`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum WarpMode {
+    /// Maps the image onto a parallelogram. The bottom right corner follows from the other ones.
+    Affine,
+    /// Maps the image onto an arbitrary quadrilateral as if it were printed on a tilted plane.
+    Perspective,
+}
+
+/// Maps the image onto a quadrilateral within the image.
+///
+/// The quadrilateral is described by the insets of the corners of the image in the order top
+/// left, top right, bottom right and bottom left. An inset is the distance by which a corner is
+/// moved towards the inside of the image, given as fraction of the width and the height. Insets
+/// never move corners outside of the image, so that the text stays on the canvas.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Warp {
+    mode: WarpMode,
+    insets: [(f32, f32); 4],
+    jitter: f32,
+    background: SerdeColor,
+}
+
+impl Warp {
+    /// Maps the image onto the parallelogram given by the insets of the top left, top right and
+    /// bottom left corner.
+    pub fn affine(insets: [(f32, f32); 3]) -> Warp {
+        Warp {
+            mode: WarpMode::Affine,
+            insets: [insets[0], insets[1], (0.0, 0.0), insets[2]],
+            jitter: 0.0,
+            background: SerdeColor::new(255, 255, 255),
+        }
+    }
+
+    /// Maps the image onto the quadrilateral given by the insets of all four corners.
+    pub fn perspective(insets: [(f32, f32); 4]) -> Warp {
+        Warp {
+            mode: WarpMode::Perspective,
+            insets,
+            ..Warp::affine([(0.0, 0.0); 3])
+        }
+    }
+
+    /// Increases each inset by a random value between 0 and `jitter`.
+    pub fn jitter(self, jitter: f32) -> Warp {
+        Warp { jitter, ..self }
+    }
+
+    pub fn background(self, background: SerdeColor) -> Warp {
+        Warp { background, ..self }
+    }
+
+    /// Returns the smallest and the largest inset of the bottom right corner of an affine warp
+    /// for all possible random values.
+    fn affine_corner_range(&self) -> ((f32, f32), (f32, f32)) {
+        let [tl, tr, _, bl] = self.insets;
+        let j = self.jitter;
+        let x = tr.0 - bl.0 + tl.0;
+        let y = bl.1 - tr.1 + tl.1;
+        ((x - j, y - j), (x + 2.0 * j, y + 2.0 * j))
+    }
+}
+
+#[typetag::serde]
+impl Filter for Warp {
+    fn apply(&self, i: &mut Image, ctx: &mut Context) -> Result<(), super::Error> {
+        let rng = ctx.rng();
+        let mut insets = self.insets;
+        if self.jitter > 0.0 {
+            for inset in insets.iter_mut() {
+                inset.0 += rng.gen_range(0.0..=self.jitter);
+                inset.1 += rng.gen_range(0.0..=self.jitter);
+            }
+        }
+
+        let (w, h) = (i.width(), i.height());
+        let (r, b) = (w as f32 - 1.0, h as f32 - 1.0);
+        let from = [(0.0, 0.0), (r, 0.0), (r, b), (0.0, b)];
+        let sign = [(1.0, 1.0), (-1.0, 1.0), (-1.0, -1.0), (1.0, -1.0)];
+        let mut to = [(0.0, 0.0); 4];
+        for k in 0..4 {
+            to[k] = (
+                from[k].0 + sign[k].0 * insets[k].0 * r,
+                from[k].1 + sign[k].1 * insets[k].1 * b,
+            );
+        }
+        if self.mode == WarpMode::Affine {
+            to[2] = (to[1].0 + to[3].0 - to[0].0, to[1].1 + to[3].1 - to[0].1);
+        }
+
+        let p = match Projection::from_control_points(from, to) {
+            Some(p) => p,
+            None => {
+                return Err(super::Error::invalid_filter_params(
+                    "Warp",
+                    "insets",
+                    "do not describe a quadrilateral",
+                ))
+            }
+        };
+        let inverse = p.invert();
+        *i = i.remap(self.background.to_pixl(), |x, y| inverse * (x, y));
+        ctx.map_geometry(|g| map_border(g, w, h, |x, y| p * (x, y)));
+
+        Ok(())
+    }
+
+    fn validate(
+        &self,
+        _viewbox: (u32, u32),
+        policy: &ValidationPolicy,
+    ) -> Result<(), super::Error> {
+        let invalid =
+            |field, reason: &str| Err(super::Error::invalid_filter_params("Warp", field, reason));
+        // corners which are moved by half of the image or more would cross each other
+        let max = policy.max_distortion.min(0.49) as f32;
+        let in_range = |v: f32| v >= 0.0 && v <= max;
+
+        if !in_range(self.jitter) {
+            return invalid("jitter", &format!("must be between 0.0 and {}", max));
+        }
+
+        let corners: &[usize] = match self.mode {
+            WarpMode::Affine => &[0, 1, 3],
+            WarpMode::Perspective => &[0, 1, 2, 3],
+        };
+        for &(x, y) in corners.iter().map(|&k| &self.insets[k]) {
+            if !in_range(x)
+                || !in_range(y)
+                || !in_range(x + self.jitter)
+                || !in_range(y + self.jitter)
+            {
+                return invalid(
+                    "insets",
+                    &format!("must be between 0.0 and {} including the jitter", max),
+                );
+            }
+        }
+
+        if self.mode == WarpMode::Affine {
+            let (lo, hi) = self.affine_corner_range();
+            if !in_range(lo.0) || !in_range(lo.1) || !in_range(hi.0) || !in_range(hi.1) {
+                return invalid(
+                    "insets",
+                    &format!(
+                        "must move the bottom right corner by between 0.0 and {} towards the inside",
+                        max
+                    ),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn cost(&self, viewbox: (u32, u32)) -> u64 {
+        super::remap_cost(viewbox)
+    }
+}
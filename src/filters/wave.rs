@@ -1,12 +1,20 @@
+use rand::Rng;
+use std::cmp::min;
 use std::f64::consts;
 
-use filters::Filter;
-use images::Image;
+use filters::{Context, Filter, ValidationPolicy};
+use images::{Image, Pixl};
+use Geometry;
 
 #[derive(Clone, Debug, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Direction {
+    /// The wave runs along the x axis and moves the columns of the image up and down.
     HORIZONTAL,
+    /// The wave runs along the y axis and moves the rows of the image left and right.
     VERTICAL,
+    /// Combines a horizontal and a vertical wave.
+    BOTH,
 }
 
 #[cfg(feature = "serde")]
@@ -15,6 +23,7 @@ impl serde::Serialize for Direction {
         match self {
             Direction::HORIZONTAL => s.serialize_str("horizontal"),
             Direction::VERTICAL => s.serialize_str("vertical"),
+            Direction::BOTH => s.serialize_str("both"),
         }
     }
 }
@@ -31,17 +40,38 @@ impl<'de> serde::Deserialize<'de> for Direction {
         match s.as_str() {
             "horizontal" => Ok(Direction::HORIZONTAL),
             "vertical" => Ok(Direction::VERTICAL),
+            "both" => Ok(Direction::BOTH),
             _ => Err(serde::de::Error::custom("invalid direction")),
         }
     }
 }
 
+/// One sine of a wave.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Harmonic {
+    /// The number of periods across the image.
+    f: f64,
+    /// The amplitude in pixels.
+    amp: f64,
+}
+
+/// Moves the columns or the rows of the image along a sum of sines.
+///
+/// The first sine is given by `f` and `amp`, further sines can be added via `harmonic`. Pixels
+/// are interpolated, so that the image has no holes after the wave has been applied.
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Wave {
     f: f64,
     amp: f64,
     d: Direction,
+    #[cfg_attr(feature = "serde", serde(default))]
+    harmonics: Vec<Harmonic>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    phase: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    random_phase: bool,
 }
 
 impl Wave {
@@ -50,6 +80,9 @@ impl Wave {
             f,
             amp,
             d: Direction::HORIZONTAL,
+            harmonics: vec![],
+            phase: 0.0,
+            random_phase: false,
         }
     }
 
@@ -67,56 +100,175 @@ impl Wave {
         }
     }
 
+    /// Applies the wave horizontally and vertically at the same time.
+    pub fn both(self) -> Wave {
+        Wave {
+            d: Direction::BOTH,
+            ..self
+        }
+    }
+
     pub fn direction(self, d: Direction) -> Wave {
         Wave { d, ..self }
     }
+
+    /// Adds a sine with `f` periods across the image and the amplitude `amp` to the wave.
+    pub fn harmonic(mut self, f: f64, amp: f64) -> Wave {
+        self.harmonics.push(Harmonic { f, amp });
+        self
+    }
+
+    /// Sets the phase in radians at which the sines start.
+    pub fn phase(self, phase: f64) -> Wave {
+        Wave { phase, ..self }
+    }
+
+    /// Adds a random phase to each sine whenever the wave is applied.
+    pub fn random_phase(self) -> Wave {
+        Wave {
+            random_phase: true,
+            ..self
+        }
+    }
+
+    /// Returns all sines of the wave.
+    fn sines(&self) -> Vec<Harmonic> {
+        let first = Harmonic {
+            f: self.f,
+            amp: self.amp,
+        };
+        Some(first)
+            .into_iter()
+            .chain(self.harmonics.iter().cloned())
+            .collect()
+    }
+
+    /// Returns the phase of each sine.
+    fn phases(&self, ctx: &mut Context) -> Vec<f64> {
+        let rng = ctx.rng();
+        (0..self.harmonics.len() + 1)
+            .map(|_| {
+                if self.random_phase {
+                    self.phase + rng.gen_range(0.0..consts::TAU)
+                } else {
+                    self.phase
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the number of pixels by which each of the `n` rows or columns along the direction
+    /// of the wave is moved.
+    fn displacements(sines: &[Harmonic], n: u32, phases: &[f64]) -> Vec<f32> {
+        (0..n)
+            .map(|v| {
+                sines
+                    .iter()
+                    .zip(phases)
+                    .map(|(s, p)| (v as f64 * consts::TAU * s.f / n as f64 + p).sin() * s.amp)
+                    .sum::<f64>() as f32
+            })
+            .collect()
+    }
+
+    /// Returns the smallest and largest displacement of the positions `from` to `to`.
+    fn displacement_range(offsets: &[f32], from: u32, to: u32) -> (i64, i64) {
+        let d = &offsets[from as usize..=to as usize];
+        let lo = d.iter().cloned().fold(f32::MAX, f32::min);
+        let hi = d.iter().cloned().fold(f32::MIN, f32::max);
+        (lo.floor() as i64, hi.ceil() as i64)
+    }
+
+    /// Returns the area which contains the pixels of `g` after the columns have been moved by
+    /// `columns` and the rows by `rows`.
+    fn map_geometry(&self, g: &Geometry, columns: &[f32], rows: &[f32]) -> Geometry {
+        let (w, h) = (columns.len() as u32, rows.len() as u32);
+        let mut g = g.clone();
+        if self.d != Direction::VERTICAL {
+            let (lo, hi) = Self::displacement_range(columns, g.left, g.right);
+            g = g.translate(0, -hi, w, h).union(&g.translate(0, -lo, w, h));
+        }
+        if self.d != Direction::HORIZONTAL {
+            let (lo, hi) = Self::displacement_range(rows, g.top, g.bottom);
+            g = g.translate(-hi, 0, w, h).union(&g.translate(-lo, 0, w, h));
+        }
+        g
+    }
 }
 
-// TODO randomize offset
 #[typetag::serde]
-
 impl Filter for Wave {
-    fn apply(&self, i: &mut Image) -> Result<(), super::Error> {
-        let o = i.clone();
-        i.clear();
-        match self.d {
-            Direction::HORIZONTAL => {
-                // height of image changes
-                for x in 0..i.width() {
-                    let f =
-                        (x as f64 * 2.0 * consts::PI * self.f / i.width() as f64).sin() * self.amp;
-                    for y in 0..i.height() {
-                        let ny = y as i32 - f as i32;
-                        if ny >= 0 && ny < i.height() as i32 {
-                            i.put_pixel(x, ny as u32, o.get_pixel(x, y));
-                        }
-                    }
-                }
-            }
-            Direction::VERTICAL => {
-                for y in 0..i.height() {
-                    let f =
-                        (y as f64 * 2.0 * consts::PI * self.f / i.width() as f64).sin() * self.amp;
-                    for x in 0..i.width() {
-                        let nx = x as i32 - f as i32;
-                        if nx >= 0 && nx < i.width() as i32 {
-                            i.put_pixel(nx as u32, y, o.get_pixel(x, y));
-                        }
-                    }
-                }
+    fn apply(&self, i: &mut Image, ctx: &mut Context) -> Result<(), super::Error> {
+        let (w, h) = (i.width(), i.height());
+        let phases = [self.phases(ctx), self.phases(ctx)];
+        let sines = self.sines();
+        let horizontal = self.d != Direction::VERTICAL;
+        let vertical = self.d != Direction::HORIZONTAL;
+
+        // the displacement only depends on the column or the row of a pixel
+        let offsets = |enabled: bool, n: u32, phases: &[f64]| -> Vec<f32> {
+            if enabled {
+                Self::displacements(&sines, n, phases)
+            } else {
+                vec![0.0; n as usize]
             }
-        }
+        };
+        let columns = offsets(horizontal, w, &phases[0]);
+        let rows = offsets(vertical, h, &phases[1]);
+
+        *i = i.remap(Pixl::white(), |x, y| {
+            (x + rows[y as usize], y + columns[x as usize])
+        });
+        ctx.map_geometry(|g| Some(self.map_geometry(g, &columns, &rows)));
 
         Ok(())
     }
 
-    fn validate(&self, _viewbox: (u32, u32)) -> Result<(), super::Error> {
-        if self.f < 0.0 || self.amp < 0.0 {
-            return Err("f and amp must be greater than 0".into());
+    fn cost(&self, viewbox: (u32, u32)) -> u64 {
+        // each sine is evaluated once per column and once per row
+        let sines = self.harmonics.len() as u64 + 1;
+        let offsets = (viewbox.0 as u64 + viewbox.1 as u64).saturating_mul(sines);
+        super::remap_cost(viewbox).saturating_add(offsets)
+    }
+
+    fn validate(&self, viewbox: (u32, u32), policy: &ValidationPolicy) -> Result<(), super::Error> {
+        let invalid =
+            |field, reason: &str| Err(super::Error::invalid_filter_params("Wave", field, reason));
+
+        if self.harmonics.len() >= policy.max_harmonics as usize {
+            return invalid(
+                "harmonics",
+                &format!(
+                    "a wave must consist of at most {} sines",
+                    policy.max_harmonics
+                ),
+            );
+        }
+
+        for s in self.sines() {
+            if s.f.is_nan() || s.f < 0.0 || s.f >= 65535.0 {
+                return invalid("f", "must be between 0.0 and 65535.0 (u16::MAX)");
+            }
+
+            if s.amp.is_nan() || s.amp < 0.0 || s.amp >= 65535.0 {
+                return invalid("amp", "must be between 0.0 and 65535.0 (u16::MAX)");
+            }
+        }
+
+        if !self.phase.is_finite() {
+            return invalid("phase", "must be a finite number");
         }
 
-        if self.f >= 65535.0 || self.amp >= 65535.0 {
-            return Err("f and amp must be less than 65535.0 (u16::MAX)".into());
+        let size = min(viewbox.0, viewbox.1) as f64;
+        let amp: f64 = self.sines().iter().map(|s| s.amp).sum();
+        if amp > policy.max_distortion * size {
+            return invalid(
+                "amp",
+                &format!(
+                    "the sum of all amplitudes must be at most {} times the smaller side of the viewbox",
+                    policy.max_distortion
+                ),
+            );
         }
 
         Ok(())
@@ -1,4 +1,4 @@
-use base64::decode;
+use base64::Engine;
 use serde_json;
 use std::collections::HashMap;
 
@@ -7,32 +7,49 @@ pub trait Font {
 
     fn chars(&self) -> Vec<char>;
 
+    /// Returns the number of pixels by which the glyph of `letter` extends below the baseline.
+    fn descent(&self, _letter: char) -> u32 {
+        0
+    }
+
     /// Returns None if letter does not exist or if letter could not decoded.
     fn png(&self, letter: char) -> Option<Vec<u8>> {
         match self.png_as_base64(letter) {
             None => None,
-            Some(s) => match decode(s) {
-                Err(_) => None,
-                Ok(v) => Some(v),
-            },
+            Some(s) => base64::engine::general_purpose::STANDARD.decode(s).ok(),
         }
     }
 }
 
 pub struct Default {
     data: HashMap<char, String>,
+    descents: HashMap<char, u32>,
 }
 
 impl Default {
     /// Create a new default font
     pub fn new() -> Default {
-        Self::from_json(include_str!("font_default.json")).expect("Failed to load default font")
+        let mut f = Self::from_json(include_str!("font_default.json"))
+            .expect("Failed to load default font");
+        f.descents = [
+            ('g', 13),
+            ('j', 12),
+            ('p', 12),
+            ('q', 12),
+            ('y', 11),
+            ('Q', 8),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        f
     }
 
     /// Create a new font from a json string
     pub fn from_json(json: &str) -> Result<Default, serde_json::Error> {
         Ok(Default {
             data: serde_json::from_str(json)?,
+            descents: HashMap::new(),
         })
     }
 }
@@ -43,7 +60,14 @@ impl Font for Default {
     }
 
     fn chars(&self) -> Vec<char> {
-        self.data.keys().cloned().collect()
+        let mut v: Vec<char> = self.data.keys().cloned().collect();
+        // sorted to make CAPTCHAs from seeded random number generators reproducible
+        v.sort_unstable();
+        v
+    }
+
+    fn descent(&self, letter: char) -> u32 {
+        self.descents.get(&letter).cloned().unwrap_or(0)
     }
 }
 
@@ -60,7 +84,7 @@ mod tests {
         assert!(f.png_as_base64('a').is_some());
         assert!(f.png('a').is_some());
         for i in f.chars() {
-            assert!(Image::from_png(f.png(i).unwrap()).is_some());
+            assert!(Image::from_png(f.png(i).unwrap()).is_ok());
         }
     }
 }
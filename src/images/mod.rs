@@ -1,8 +1,8 @@
-use image::ImageResult as Result;
 use std::cmp::{max, min};
 use std::path::Path;
 
 use image::{load_from_memory, ImageBuffer, Rgba, RgbaImage};
+use Error;
 
 #[derive(Clone, Copy)]
 pub struct Pixl {
@@ -12,6 +12,8 @@ pub struct Pixl {
 #[derive(Clone)]
 pub struct Image {
     img: RgbaImage,
+    /// The coverage of each pixel with text between 0 and 255, see `darken_image`.
+    text: Vec<u8>,
 }
 
 impl Pixl {
@@ -25,6 +27,10 @@ impl Pixl {
         Pixl { rgb: [r, g, b, a] }
     }
 
+    pub fn white() -> Pixl {
+        Pixl::new(255, 255, 255)
+    }
+
     pub fn black() -> Pixl {
         Pixl::new(0, 0, 0)
     }
@@ -33,6 +39,10 @@ impl Pixl {
         Pixl::new(255, 0, 0)
     }
 
+    pub fn rgba(&self) -> [u8; 4] {
+        self.rgb
+    }
+
     pub fn invert(&mut self) {
         self.rgb[0] = 255 - self.rgb[0];
         self.rgb[1] = 255 - self.rgb[1];
@@ -45,39 +55,90 @@ impl Image {
         Rgba::<u8>([255, 255, 255, 255])
     }
 
-    pub fn from_png(v: Vec<u8>) -> Option<Image> {
+    /// Returns an image of `img` which contains no text.
+    fn from_rgba(img: RgbaImage) -> Image {
+        let n = img.width() as usize * img.height() as usize;
+        Image {
+            img,
+            text: vec![0; n],
+        }
+    }
+
+    pub fn from_png(v: Vec<u8>) -> Result<Image, Error> {
         match load_from_memory(&v) {
-            Err(_) => None,
-            Ok(i) => Some(Image { img: i.to_rgba8() }),
+            Err(e) => Err(Error::Decode(e)),
+            Ok(i) => Ok(Image::from_rgba(i.to_rgba8())),
         }
     }
 
     pub fn new(w: u32, h: u32) -> Image {
-        Image {
-            img: ImageBuffer::from_pixel(w, h, Self::pixel_white()),
+        Image::from_rgba(ImageBuffer::from_pixel(w, h, Self::pixel_white()))
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width() as usize + x as usize
+    }
+
+    /// Returns the coverage of the pixel at `x`, `y` with text between 0 and 255.
+    pub fn text_coverage(&self, x: u32, y: u32) -> u8 {
+        self.text[self.index(x, y)]
+    }
+
+    /// Sets the coverage of the pixel at `x`, `y` with text.
+    pub fn set_text_coverage(&mut self, x: u32, y: u32, coverage: u8) {
+        if x < self.width() && y < self.height() {
+            let k = self.index(x, y);
+            self.text[k] = coverage;
         }
     }
 
+    /// Copies the color and the coverage with text of the pixel `sx`, `sy` of `src` to the pixel
+    /// at `x`, `y`.
+    pub fn copy_pixel(&mut self, x: u32, y: u32, src: &Image, sx: u32, sy: u32) {
+        self.put_pixel(x, y, src.get_pixel(sx, sy));
+        self.set_text_coverage(x, y, src.text_coverage(sx, sy));
+    }
+
+    /// Replaces the black of the text with `color`.
+    ///
+    /// Only pixels which are covered by text are changed, see `darken_image`. A pixel which is
+    /// partly covered, like the antialiased edges of the text, is the black of the text on top of
+    /// the rest of the pixel, so that the black share is replaced according to the coverage.
     pub fn set_color(&mut self, color: &[u8; 4]) {
-        // TODO: optimize
-        for y in 0..self.img.height() {
-            for x in 0..self.img.width() {
-                let c = *self.img.get_pixel(x, y);
-                if c[0] == 0 {
-                    // if red channel is 0 we assume it's a black pixel
-                    let rgb = Rgba::<u8>(*color);
-                    self.img.put_pixel(x, y, rgb);
-                }
+        for (p, t) in self.img.pixels_mut().zip(&self.text) {
+            if *t == 0 {
+                continue;
+            }
+            let coverage = *t as f32 / 255.0;
+            for k in 0..3 {
+                p[k] = (p[k] as f32 + color[k] as f32 * coverage)
+                    .round()
+                    .min(255.0) as u8;
             }
+            p[3] = (p[3] as f32 * (1.0 - coverage) + color[3] as f32 * coverage).round() as u8;
         }
     }
 
+    /// Sets the color of the pixel at `x`, `y`. Its coverage with text is not changed.
     pub fn put_pixel(&mut self, x: u32, y: u32, p: Pixl) {
         if x < self.img.width() && y < self.img.height() {
             self.img.put_pixel(x, y, Rgba::<u8>(p.rgb));
         }
     }
 
+    /// Mixes the pixel at `x`, `y` with `p`. `alpha` is the weight of `p` between 0 and 1.
+    pub fn blend_pixel(&mut self, x: u32, y: u32, p: Pixl, alpha: f32) {
+        if x < self.img.width() && y < self.img.height() {
+            let a = alpha.clamp(0.0, 1.0);
+            let o = self.img.get_pixel(x, y).0;
+            let mut rgb = [0; 4];
+            for k in 0..4 {
+                rgb[k] = (o[k] as f32 * (1.0 - a) + p.rgb[k] as f32 * a).round() as u8;
+            }
+            self.img.put_pixel(x, y, Rgba::<u8>(rgb));
+        }
+    }
+
     pub fn get_pixel(&self, x: u32, y: u32) -> Pixl {
         let p = *self.img.get_pixel(x, y);
         Pixl {
@@ -93,8 +154,8 @@ impl Image {
         self.img.height()
     }
 
-    pub fn save(&self, p: &Path) -> Result<()> {
-        self.img.save(p)
+    pub fn save(&self, p: &Path) -> Result<(), Error> {
+        self.img.save(p).map_err(Error::Encode)
     }
 
     pub fn draw_polygon(&mut self, poly: &[imageproc::point::Point<i32>], color: Rgba<u8>) {
@@ -121,19 +182,189 @@ impl Image {
         }
     }
 
+    /// Returns a copy of the image scaled by the factor `f`.
+    ///
+    /// The image is resampled with a Catmull-Rom filter. The returned image is at least one pixel
+    /// wide and high.
+    pub fn scale(&self, f: f32) -> Image {
+        let w = max((self.width() as f32 * f).round() as u32, 1);
+        let h = max((self.height() as f32 * f).round() as u32, 1);
+        Image::from_rgba(image::imageops::resize(
+            &self.img,
+            w,
+            h,
+            image::imageops::FilterType::CatmullRom,
+        ))
+    }
+
+    /// Returns a copy of the image rotated by `degrees` around its center.
+    ///
+    /// The returned image is enlarged so that it exactly contains the rotated image. Areas not
+    /// covered by the rotated image are white.
+    pub fn rotate(&self, degrees: f32) -> Image {
+        let theta = degrees.to_radians();
+        let (s, c) = (theta.sin().abs(), theta.cos().abs());
+        let (w, h) = (self.width() as f32, self.height() as f32);
+        let nw = (w * c + h * s).ceil() as u32;
+        let nh = (w * s + h * c).ceil() as u32;
+
+        // the canvas must contain the image before and after the rotation
+        let cw = max(nw, self.width());
+        let ch = max(nh, self.height());
+        let mut i = Image::new(cw, ch);
+        i.add_image((cw - self.width()) / 2, (ch - self.height()) / 2, self);
+        let r = imageproc::geometric_transformations::rotate_about_center(
+            &i.img,
+            theta,
+            imageproc::geometric_transformations::Interpolation::Bilinear,
+            Self::pixel_white(),
+        );
+        Image::from_rgba(
+            image::imageops::crop_imm(&r, (cw - nw) / 2, (ch - nh) / 2, nw, nh).to_image(),
+        )
+    }
+
+    /// Returns the color at the position `x`, `y` interpolated bilinearly between the four
+    /// neighbouring pixels. Pixels outside of the image have the color `bg`.
+    pub fn sample(&self, x: f32, y: f32, bg: Pixl) -> Pixl {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let get = |px: f32, py: f32| {
+            if px < 0.0 || py < 0.0 || px >= self.width() as f32 || py >= self.height() as f32 {
+                bg.rgb
+            } else {
+                self.img.get_pixel(px as u32, py as u32).0
+            }
+        };
+        let (a, b) = (get(x0, y0), get(x0 + 1.0, y0));
+        let (c, d) = (get(x0, y0 + 1.0), get(x0 + 1.0, y0 + 1.0));
+        let mut rgb = [0; 4];
+        for k in 0..4 {
+            let top = a[k] as f32 * (1.0 - fx) + b[k] as f32 * fx;
+            let bottom = c[k] as f32 * (1.0 - fx) + d[k] as f32 * fx;
+            rgb[k] = (top * (1.0 - fy) + bottom * fy).round() as u8;
+        }
+        Pixl { rgb }
+    }
+
+    /// Returns the coverage with text at the position `x`, `y` interpolated like `sample`.
+    /// Pixels outside of the image contain no text.
+    fn sample_text(&self, x: f32, y: f32) -> u8 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let get = |px: f32, py: f32| {
+            if px < 0.0 || py < 0.0 || px >= self.width() as f32 || py >= self.height() as f32 {
+                0.0
+            } else {
+                self.text_coverage(px as u32, py as u32) as f32
+            }
+        };
+        let top = get(x0, y0) * (1.0 - fx) + get(x0 + 1.0, y0) * fx;
+        let bottom = get(x0, y0 + 1.0) * (1.0 - fx) + get(x0 + 1.0, y0 + 1.0) * fx;
+        (top * (1.0 - fy) + bottom * fy).round() as u8
+    }
+
+    /// Returns a copy of the image in which the pixel at `x`, `y` has the color at the position
+    /// `f(x, y)` of this image.
+    ///
+    /// `f` is the inverse of the transformation which is applied to the image, so that every
+    /// pixel of the result gets a color and no holes appear. Colors are interpolated with
+    /// `sample` and the coverage with text moves along with the colors.
+    pub fn remap<F: Fn(f32, f32) -> (f32, f32)>(&self, bg: Pixl, f: F) -> Image {
+        let mut i = self.clone();
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let (sx, sy) = f(x as f32, y as f32);
+                i.put_pixel(x, y, self.sample(sx, sy, bg));
+                i.set_text_coverage(x, y, self.sample_text(sx, sy));
+            }
+        }
+        i
+    }
+
+    /// Puts the color `f(x, y)` under the content of each pixel.
+    ///
+    /// The content is assumed to be drawn on white: white pixels get the color of the background,
+    /// black and fully saturated pixels keep their color and light pixels are mixed with the
+    /// background according to their lightness. Transparent pixels are additionally mixed with the
+    /// background according to their alpha.
+    pub fn fill_under<F: FnMut(u32, u32) -> Pixl>(&mut self, mut f: F) {
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let o = self.img.get_pixel(x, y).0;
+                let bg = f(x, y).rgb;
+                // the lightest channel is the share of white in the pixel
+                let white = min(o[0], min(o[1], o[2])) as u32;
+                let a = o[3] as u32;
+                let mut p = [0; 4];
+                for k in 0..3 {
+                    let c = o[k] as u32 - (255 - bg[k] as u32) * white / 255;
+                    p[k] = ((c * a + bg[k] as u32 * (255 - a) + 127) / 255) as u8;
+                }
+                p[3] = max(o[3], bg[3]);
+                self.img.put_pixel(x, y, Rgba::<u8>(p));
+            }
+        }
+    }
+
+    /// Composites the image onto white, so that transparent pixels become white.
+    pub fn flatten(&mut self) {
+        for p in self.img.pixels_mut() {
+            let a = p[3] as u32;
+            for k in 0..3 {
+                p[k] = ((p[k] as u32 * a + 255 * (255 - a) + 127) / 255) as u8;
+            }
+            p[3] = 255;
+        }
+    }
+
+    /// Enlarges the image by the given number of white pixels on each side.
+    pub fn expand(&mut self, left: u32, top: u32, right: u32, bottom: u32) {
+        let mut i = Image::new(self.width() + left + right, self.height() + top + bottom);
+        i.add_image(left, top, self);
+        *self = i;
+    }
+
     pub fn clear(&mut self) {
-        self.img = ImageBuffer::from_pixel(self.width(), self.height(), Self::pixel_white())
+        *self = Image::new(self.width(), self.height());
     }
 
     pub fn add_image(&mut self, x: u32, y: u32, i: &Image) {
         for iy in 0..i.height() {
             for ix in 0..i.width() {
-                self.put_pixel(x + ix, y + iy, i.get_pixel(ix, iy));
+                self.copy_pixel(x + ix, y + iy, i, ix, iy);
+            }
+        }
+    }
+
+    /// Draws the image `i` at the position `x`, `y` keeping the darker value of each channel.
+    ///
+    /// In contrast to `add_image` the white background of `i` does not erase dark pixels which
+    /// are already there, so that overlapping characters blend into each other. `i` is text: the
+    /// darkness of its pixels is their coverage with text, which is recolored by `set_color`.
+    pub fn darken_image(&mut self, x: u32, y: u32, i: &Image) {
+        for iy in 0..i.height() {
+            for ix in 0..i.width() {
+                let (px, py) = (x + ix, y + iy);
+                if px < self.width() && py < self.height() {
+                    let a = self.img.get_pixel(px, py).0;
+                    let b = i.img.get_pixel(ix, iy).0;
+                    let p = [
+                        min(a[0], b[0]),
+                        min(a[1], b[1]),
+                        min(a[2], b[2]),
+                        max(a[3], b[3]),
+                    ];
+                    self.img.put_pixel(px, py, Rgba::<u8>(p));
+                    let coverage = 255 - min(b[0], min(b[1], b[2]));
+                    let k = self.index(px, py);
+                    self.text[k] = max(self.text[k], coverage);
+                }
             }
         }
     }
 
-    pub fn as_png(&self) -> Option<Vec<u8>> {
+    pub fn as_png(&self) -> Result<Vec<u8>, Error> {
         let w = self.img.width() as usize;
         let h = self.img.height() as usize;
         let i = self.img.clone().into_raw();
@@ -148,11 +379,11 @@ impl Image {
             image::ColorType::Rgba8,
             image::ImageFormat::Png,
         ) {
-            Err(_) => None,
-            Ok(_) => {
-                let img = buf.into_inner().unwrap().into_inner();
-                Some(img)
-            }
+            Err(e) => Err(Error::Encode(e)),
+            Ok(_) => match buf.into_inner() {
+                Err(e) => Err(Error::Encode(image::ImageError::IoError(e.into_error()))),
+                Ok(c) => Ok(c.into_inner()),
+            },
         }
     }
 }
@@ -31,24 +31,22 @@
 //! use captcha::Captcha;
 //! use captcha::filters::{Noise, Wave, Dots};
 //!
-//! # fn main() {
+//! # fn main() -> Result<(), captcha::Error> {
 //! Captcha::new()
 //!     .add_random_chars(5)
-//!     .apply_filter(Noise::new(0.4))
-//!     .apply_filter(Wave::new(2.0, 20.0).horizontal())
-//!     .apply_filter(Wave::new(2.0, 20.0).vertical())
-//!     .view(220, 120)
-//!     .apply_filter(Dots::new(15))
+//!     .apply_filter(Noise::new(0.4))?
+//!     .apply_filter(Wave::new(2.0, 20.0).horizontal())?
+//!     .apply_filter(Wave::new(2.0, 20.0).vertical())?
+//!     .view(280, 160)?
+//!     .apply_filter(Dots::new(15))?
 //!     .as_png();
+//! # Ok(())
 //! # }
 //! ```
 
-// TODO rotate characters
-// TODO resize characters
-// TODO overlap characters
-
 extern crate base64;
 extern crate image;
+extern crate imageproc;
 extern crate rand;
 #[cfg(feature = "serde")]
 extern crate serde;
@@ -58,14 +56,15 @@ pub mod filters;
 mod fonts;
 mod images;
 mod samples;
+mod spec;
 
 pub use samples::{by_name, gen, CaptchaName, Difficulty};
+pub use spec::{CaptchaSpec, SpecIssue};
 
-use filters::Filter;
+use filters::{Context, Filter, ValidationPolicy};
 use fonts::{Default, Font};
 use images::{Image, Pixl};
 
-use image::ImageResult as Result;
 use rand::prelude::*;
 use rand::thread_rng;
 use std::cmp::{max, min};
@@ -94,10 +93,255 @@ impl Geometry {
             bottom,
         }
     }
+
+    /// Moves the area by `dx`, `dy` and clips it to a canvas of size `w` x `h`.
+    ///
+    /// Each coordinate is clamped to the canvas, so that an area which is entirely outside of
+    /// the canvas becomes a line along the nearest edge or the nearest corner pixel. Use
+    /// `translate_within` to detect such areas.
+    fn translate(&self, dx: i64, dy: i64, w: u32, h: u32) -> Geometry {
+        let clip =
+            |v: u32, d: i64, n: u32| min(max((v as i64).saturating_add(d), 0), n as i64 - 1) as u32;
+        Geometry {
+            left: clip(self.left, dx, w),
+            right: clip(self.right, dx, w),
+            top: clip(self.top, dy, h),
+            bottom: clip(self.bottom, dy, h),
+        }
+    }
+
+    /// Moves the area by `dx`, `dy` and clips it to a canvas of size `w` x `h`.
+    ///
+    /// Returns `None` if no part of the area is on the canvas.
+    fn translate_within(&self, dx: i64, dy: i64, w: u32, h: u32) -> Option<Geometry> {
+        let outside = |lo: u32, hi: u32, d: i64, n: u32| {
+            (hi as i64).saturating_add(d) < 0 || (lo as i64).saturating_add(d) >= n as i64
+        };
+        if outside(self.left, self.right, dx, w) || outside(self.top, self.bottom, dy, h) {
+            None
+        } else {
+            Some(self.translate(dx, dy, w, h))
+        }
+    }
+
+    /// Returns the smallest area which contains this area and `g`.
+    fn union(&self, g: &Geometry) -> Geometry {
+        Geometry {
+            left: min(self.left, g.left),
+            right: max(self.right, g.right),
+            top: min(self.top, g.top),
+            bottom: max(self.bottom, g.bottom),
+        }
+    }
+}
+
+/// Errors which occur when a CAPTCHA is cropped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GeometryError {
+    /// The text does not fit into a view of the given width and height.
+    TooSmall { width: u32, height: u32 },
+    /// The area is not completely within the image of the given width and height.
+    OutOfBounds {
+        area: Geometry,
+        width: u32,
+        height: u32,
+    },
+    /// The left coordinate of the area is greater than the right one or the top coordinate is
+    /// greater than the bottom one.
+    InvalidArea(Geometry),
+    /// An image of the given width and height would be larger than 16384 pixels on a side.
+    TooLarge { width: u64, height: u64 },
+}
+
+impl std::fmt::Display for GeometryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GeometryError::TooSmall { width, height } => {
+                write!(f, "the text does not fit into {}x{} pixels", width, height)
+            }
+            GeometryError::OutOfBounds {
+                area,
+                width,
+                height,
+            } => write!(
+                f,
+                "area ({}, {})-({}, {}) is not within the image of {}x{} pixels",
+                area.left, area.top, area.right, area.bottom, width, height
+            ),
+            GeometryError::InvalidArea(area) => write!(
+                f,
+                "area ({}, {})-({}, {}) is empty",
+                area.left, area.top, area.right, area.bottom
+            ),
+            GeometryError::TooLarge { width, height } => write!(
+                f,
+                "an image of {}x{} pixels exceeds {} pixels on a side",
+                width, height, MAX_IMAGE_SIZE
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GeometryError {}
+
+/// The error type of this crate.
+#[derive(Debug)]
+pub enum Error {
+    /// The image could not be encoded or written.
+    Encode(image::ImageError),
+    /// The image of a character or a background image could not be decoded.
+    Decode(image::ImageError),
+    /// A parameter of a filter has an invalid value.
+    InvalidFilterParams {
+        /// The name of the filter.
+        filter: &'static str,
+        /// The name of the parameter.
+        field: &'static str,
+        /// Why the value is invalid.
+        reason: String,
+    },
+    /// The character is not supported by the font.
+    UnknownChar(char),
+    /// The CAPTCHA could not be cropped.
+    Geometry(GeometryError),
+    /// A font could not be loaded from its JSON representation.
+    Font(serde_json::Error),
+    /// Applying filters would exceed the budget of pixel operations.
+    BudgetExceeded {
+        /// The estimated number of pixel operations.
+        cost: u64,
+        /// The maximum number of pixel operations.
+        budget: u64,
+    },
+    /// A setting of a `CaptchaSpec` which is not a filter has an invalid value.
+    InvalidSetting {
+        /// The name of the setting.
+        field: &'static str,
+        /// Why the value is invalid.
+        reason: String,
+    },
+    /// None of the background images is at least as large as the CAPTCHA.
+    BackgroundTooSmall { width: u32, height: u32 },
+}
+
+impl Error {
+    fn invalid_filter_params<S: Into<String>>(
+        filter: &'static str,
+        field: &'static str,
+        reason: S,
+    ) -> Error {
+        Error::InvalidFilterParams {
+            filter,
+            field,
+            reason: reason.into(),
+        }
+    }
+
+    fn invalid_setting<S: Into<String>>(field: &'static str, reason: S) -> Error {
+        Error::InvalidSetting {
+            field,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Encode(e) => write!(f, "could not encode the image: {}", e),
+            Error::Decode(e) => write!(f, "could not decode an image: {}", e),
+            Error::InvalidFilterParams {
+                filter,
+                field,
+                reason,
+            } => write!(
+                f,
+                "invalid value for {} of filter {}: {}",
+                field, filter, reason
+            ),
+            Error::UnknownChar(c) => write!(f, "the font does not support the character {:?}", c),
+            Error::Geometry(e) => e.fmt(f),
+            Error::Font(e) => write!(f, "could not load the font: {}", e),
+            Error::BudgetExceeded { cost, budget } => write!(
+                f,
+                "{} pixel operations exceed the budget of {}",
+                cost, budget
+            ),
+            Error::InvalidSetting { field, reason } => {
+                write!(f, "invalid value for {}: {}", field, reason)
+            }
+            Error::BackgroundTooSmall { width, height } => write!(
+                f,
+                "no background image is at least {}x{} pixels",
+                width, height
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Encode(e) | Error::Decode(e) => Some(e),
+            Error::Geometry(e) => Some(e),
+            Error::Font(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<GeometryError> for Error {
+    fn from(e: GeometryError) -> Error {
+        Error::Geometry(e)
+    }
+}
+
+/// Determines which point of a character is placed onto the line along which the text is written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VerticalAlign {
+    /// The center of each character is placed onto the line.
+    Center,
+    /// The baseline of each character is placed onto the line, i.e. characters stand on the
+    /// line and only descenders (like in `g` or `y`) extend below it.
+    Baseline,
+}
+
+/// The shape of the line along which the text is written.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TextPath {
+    /// A horizontal straight line.
+    Straight,
+    /// A sine curve with the given amplitude and period (both in pixels). The phase of the curve
+    /// is chosen randomly.
+    Sine { amplitude: f32, period: f32 },
+    /// A circular arc with the given radius (in pixels) which starts horizontally at the first
+    /// character. A positive radius bends the line downwards, a negative one upwards.
+    Arc { radius: f32 },
 }
 
 pub type Captcha = RngCaptcha<ThreadRng>;
 
+const CANVAS_WIDTH: u32 = 400;
+const CANVAS_HEIGHT: u32 = 300;
+/// The largest factor by which characters are scaled.
+const MAX_SCALE: f32 = 64.0;
+/// The largest width and height of an image, larger ones cannot be allocated reliably.
+const MAX_IMAGE_SIZE: u32 = 16384;
+
+/// Returns the range between `min` and `max` with both bounds clamped to `lo..=hi`. NaN is
+/// replaced by `default`.
+fn clamp_range(min: f32, max: f32, default: f32, lo: f32, hi: f32) -> (f32, f32) {
+    let clamp = |v: f32| if v.is_nan() { default } else { v.clamp(lo, hi) };
+    let (min, max) = (clamp(min), clamp(max));
+    if min <= max {
+        (min, max)
+    } else {
+        (max, min)
+    }
+}
+
 /// A CAPTCHA.
 pub struct RngCaptcha<T> {
     img: Image,
@@ -106,31 +350,64 @@ pub struct RngCaptcha<T> {
     chars: Vec<char>,
     use_font_chars: Vec<char>,
     color: Option<[u8; 4]>,
+    backgrounds: Vec<Image>,
+    background_choice: [f32; 3],
+    rotation: (f32, f32),
+    scale: (f32, f32),
+    spacing: i32,
+    spacing_jitter: u32,
+    auto_resize: bool,
+    align: VerticalAlign,
+    path: TextPath,
+    path_phase: f32,
+    vertical_jitter: u32,
+    line: (i64, i64),
+    glyphs: Vec<Option<Geometry>>,
+    budget: Option<u64>,
+    cost: u64,
     rng: T,
 }
 
 impl<T: rand::Rng + rand::RngCore> RngCaptcha<T> {
     pub fn from_rng(rng: T) -> RngCaptcha<T> {
-        // TODO fixed width + height
-        let w = 400;
-        let h = 300;
+        let w = CANVAS_WIDTH;
+        let h = CANVAS_HEIGHT;
         let f = Box::new(Default::new());
         RngCaptcha::<T> {
             use_font_chars: f.chars(),
             img: Image::new(w, h),
             font: f,
-            text_area: Geometry {
-                left: w / 4,
-                right: w / 4,
-                top: h / 2,
-                bottom: h / 2,
-            },
+            text_area: Self::initial_text_area(w, h),
             chars: vec![],
             color: None,
+            backgrounds: vec![],
+            background_choice: [0.0; 3],
+            rotation: (0.0, 0.0),
+            scale: (1.0, 1.0),
+            spacing: 0,
+            spacing_jitter: 0,
+            auto_resize: true,
+            align: VerticalAlign::Center,
+            path: TextPath::Straight,
+            path_phase: 0.0,
+            vertical_jitter: 0,
+            line: (w as i64 / 4, h as i64 / 2),
+            glyphs: vec![],
+            budget: None,
+            cost: 0,
             rng,
         }
     }
 
+    fn initial_text_area(w: u32, h: u32) -> Geometry {
+        Geometry {
+            left: w / 4,
+            right: w / 4,
+            top: h / 2,
+            bottom: h / 2,
+        }
+    }
+
     /// Returns an empty CAPTCHA.
     pub fn new() -> Captcha {
         Captcha::from_rng(thread_rng())
@@ -139,23 +416,65 @@ impl<T: rand::Rng + rand::RngCore> RngCaptcha<T> {
     /// Applies the filter `f` to the CAPTCHA.
     ///
     /// This method is used to add noise, grids, etc or to transform the shape of the CAPTCHA.
-    pub fn apply_filter<F: Filter>(
-        &mut self,
-        f: F,
-    ) -> std::result::Result<&mut Self, filters::Error> {
-        f.apply(&mut self.img)?;
-        Ok(self)
+    /// The filter is validated with `ValidationPolicy::permissive()` for the current size of the
+    /// image first, so that parameters which the filter cannot be applied with result in
+    /// `Error::InvalidFilterParams` instead of a panic.
+    pub fn apply_filter<F: Filter>(&mut self, f: F) -> Result<&mut Self, Error> {
+        self.apply_filter_dyn(&f)
     }
 
     /// Same as `apply_filter` but supports dynamic dispatch.
-    pub fn apply_filter_dyn(
-        &mut self,
-        f: &dyn Filter,
-    ) -> std::result::Result<&mut Self, filters::Error> {
-        f.apply(&mut self.img)?;
+    pub fn apply_filter_dyn(&mut self, f: &dyn Filter) -> Result<&mut Self, Error> {
+        let viewbox = (self.img.width(), self.img.height());
+        f.validate(viewbox, &ValidationPolicy::permissive())?;
+        self.charge(f.cost(viewbox))?;
+
+        let mut ctx = Context::new(
+            &mut self.rng,
+            &mut self.text_area,
+            &mut self.glyphs,
+            self.font.as_ref(),
+        );
+        f.apply(&mut self.img, &mut ctx)?;
         Ok(self)
     }
 
+    /// Limits the number of pixel operations which filters and the resizing of the image may
+    /// perform.
+    ///
+    /// Before a filter is applied its cost is estimated for the current size of the image.
+    /// Enlarging the canvas for a character and cropping via `view` or `extract` cost one
+    /// operation per pixel of each image which is copied. If the cost together with the cost of
+    /// everything which has already been done exceeds `budget` nothing is changed and
+    /// `Error::BudgetExceeded` is returned. By default the number of operations is not limited.
+    pub fn set_cost_budget(&mut self, budget: u64) -> &mut Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Returns the estimated number of pixel operations of all filters which have been applied
+    /// and of all copies of the image, see `set_cost_budget`.
+    pub fn cost(&self) -> u64 {
+        self.cost
+    }
+
+    /// Returns `Error::BudgetExceeded` if `cost` further pixel operations exceed the budget.
+    fn check_budget(&self, cost: u64) -> Result<u64, Error> {
+        let cost = self.cost.saturating_add(cost);
+        match self.budget {
+            Some(budget) if cost > budget => Err(Error::BudgetExceeded { cost, budget }),
+            _ => Ok(cost),
+        }
+    }
+
+    /// Adds `cost` to the pixel operations which have been performed.
+    ///
+    /// Returns `Error::BudgetExceeded` without adding the cost if the budget would be exceeded.
+    fn charge(&mut self, cost: u64) -> Result<(), Error> {
+        self.cost = self.check_budget(cost)?;
+        Ok(())
+    }
+
     /// Sets another font that is used for the characters.
     ///
     /// Calling this method does not have an effect on the font of the characters which have already
@@ -169,80 +488,392 @@ impl<T: rand::Rng + rand::RngCore> RngCaptcha<T> {
         self
     }
 
+    /// Sets the color of the text.
+    ///
+    /// The color is applied when the CAPTCHA is converted into an image and replaces the black of
+    /// the characters. The antialiased edges of the characters are mixed with the color according
+    /// to how much of each pixel they cover. Everything which has been drawn by filters, e.g. a
+    /// `Background`, dots or noise, keeps its color.
     pub fn set_color(&mut self, color: [u8; 4]) -> &mut Self {
         self.color = Some(color);
         self
     }
 
+    /// Sets PNG images of which one is used as background.
+    ///
+    /// When the CAPTCHA is converted into an image one of the background images which are at
+    /// least as large as the CAPTCHA is chosen randomly and a random crop of the size of the
+    /// CAPTCHA is taken from it. Transparent parts of the background images are white. The
+    /// CAPTCHA is composited onto the crop, so that its white areas, e.g. the boxes around the
+    /// characters, show the background while the text stays on top. Converting the CAPTCHA fails
+    /// with `Error::BackgroundTooSmall` if no background image is large enough.
+    ///
+    /// Returns `Error::Decode` if an image cannot be decoded.
+    pub fn set_background_images(&mut self, pngs: &[Vec<u8>]) -> Result<&mut Self, Error> {
+        let mut backgrounds = vec![];
+        for png in pngs {
+            let mut i = Image::from_png(png.clone())?;
+            i.flatten();
+            backgrounds.push(i);
+        }
+        self.backgrounds = backgrounds;
+        // the image and the crop are chosen now, so that they do not change between conversions
+        self.background_choice = [self.rng.gen(), self.rng.gen(), self.rng.gen()];
+        Ok(self)
+    }
+
+    /// Sets the range of angles (in degrees) by which characters are rotated.
+    ///
+    /// Each character which is added after this method is called is rotated by its own angle
+    /// which is randomly chosen between `min` and `max` (inclusive), e.g. `set_rotation(-25.0,
+    /// 25.0)`. Positive angles rotate clockwise. By default characters are not rotated.
+    ///
+    /// Angles are clamped to `-360.0..=360.0`, which covers every orientation, and NaN is
+    /// treated as `0.0`.
+    pub fn set_rotation(&mut self, min: f32, max: f32) -> &mut Self {
+        self.rotation = clamp_range(min, max, 0.0, -360.0, 360.0);
+        self
+    }
+
+    /// Sets the factor by which characters are scaled.
+    ///
+    /// The factor applies to each character which is added after this method is called. A factor
+    /// of `1.0` keeps the native size of the font's glyphs, which is the default. See
+    /// `set_scale_range` for factors which are out of range.
+    pub fn set_scale(&mut self, f: f32) -> &mut Self {
+        self.set_scale_range(f, f)
+    }
+
+    /// Sets the range of factors by which characters are scaled.
+    ///
+    /// Each character which is added after this method is called is scaled by its own factor
+    /// which is randomly chosen between `min` and `max` (inclusive). This varies the size of the
+    /// characters within one CAPTCHA.
+    ///
+    /// Factors are clamped to `0.0..=64.0` and NaN is treated as `1.0`. A character is at least
+    /// one pixel wide and high however small the factor is.
+    pub fn set_scale_range(&mut self, min: f32, max: f32) -> &mut Self {
+        self.scale = clamp_range(min, max, 1.0, 0.0, MAX_SCALE);
+        self
+    }
+
+    /// Sets the spacing in pixels between characters.
+    ///
+    /// Positive values move the characters apart, negative values make them overlap. Overlapping
+    /// characters are blended into each other. The default is `0`.
+    pub fn set_spacing(&mut self, spacing: i32) -> &mut Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Sets the maximum number of pixels by which the spacing between two characters randomly
+    /// deviates from the value set via `set_spacing`.
+    pub fn set_spacing_jitter(&mut self, jitter: u32) -> &mut Self {
+        self.spacing_jitter = jitter;
+        self
+    }
+
+    /// Sets the size of the canvas on which the characters are drawn.
+    ///
+    /// By default the canvas has a size of 400x300 pixels and is enlarged automatically whenever
+    /// a character does not fit onto it. After calling this method the canvas has the given size
+    /// and is no longer enlarged, i.e. characters which would extend beyond an edge are moved onto
+    /// the canvas and characters which are larger than the canvas are not added. Characters which
+    /// have already been added are kept and centered on the new canvas.
+    ///
+    /// Returns `GeometryError::TooLarge` if a side exceeds `MAX_IMAGE_SIZE` pixels. Creating the
+    /// canvas is charged against the cost budget.
+    pub fn set_canvas_size(&mut self, w: u32, h: u32) -> Result<&mut Self, Error> {
+        if w > MAX_IMAGE_SIZE || h > MAX_IMAGE_SIZE {
+            return Err(GeometryError::TooLarge {
+                width: w as u64,
+                height: h as u64,
+            }
+            .into());
+        }
+        let w = max(w, 1);
+        let h = max(h, 1);
+        self.charge(filters::pixels((w, h)))?;
+        let a = self.text_area.clone();
+        let mut i = Image::new(w, h);
+        if self.chars.is_empty() {
+            self.text_area = Self::initial_text_area(w, h);
+            self.line = (w as i64 / 4, h as i64 / 2);
+        } else {
+            let dx = (w as i64 - (a.right - a.left + 1) as i64) / 2 - a.left as i64;
+            let dy = (h as i64 - (a.bottom - a.top + 1) as i64) / 2 - a.top as i64;
+            for sy in a.top..=a.bottom {
+                for sx in a.left..=a.right {
+                    let (x, y) = (sx as i64 + dx, sy as i64 + dy);
+                    if x >= 0 && y >= 0 {
+                        i.copy_pixel(x as u32, y as u32, &self.img, sx, sy);
+                    }
+                }
+            }
+            self.translate(dx, dy, w, h);
+        }
+        self.img = i;
+        self.auto_resize = false;
+        Ok(self)
+    }
+
+    /// Moves the text by `dx`, `dy` on a canvas of size `w` x `h`.
+    ///
+    /// Only the geometry of the text is updated, the pixels have to be moved by the caller.
+    /// Characters which are moved off the canvas entirely lose their area.
+    fn translate(&mut self, dx: i64, dy: i64, w: u32, h: u32) {
+        self.text_area = self.text_area.translate(dx, dy, w, h);
+        for g in self.glyphs.iter_mut() {
+            *g = g.as_ref().and_then(|g| g.translate_within(dx, dy, w, h));
+        }
+        self.line = (self.line.0 + dx, self.line.1 + dy);
+    }
+
+    /// Sets how characters are placed vertically onto the line along which the text is written.
+    ///
+    /// The default is `VerticalAlign::Center`.
+    pub fn set_vertical_align(&mut self, align: VerticalAlign) -> &mut Self {
+        self.align = align;
+        self
+    }
+
+    /// Sets the shape of the line along which the text is written.
+    ///
+    /// The default is `TextPath::Straight`. The amplitude of a sine and the radius of an arc are
+    /// clamped to `-16384.0..=16384.0` and NaN is treated as `0.0`.
+    pub fn set_text_path(&mut self, path: TextPath) -> &mut Self {
+        let max = MAX_IMAGE_SIZE as f32;
+        let clamp = |v: f32| if v.is_nan() { 0.0 } else { v.clamp(-max, max) };
+        self.path_phase = self.rng.gen_range(0.0..std::f32::consts::TAU);
+        self.path = match path {
+            TextPath::Straight => TextPath::Straight,
+            TextPath::Sine { amplitude, period } => TextPath::Sine {
+                amplitude: clamp(amplitude),
+                period,
+            },
+            TextPath::Arc { radius } => TextPath::Arc {
+                radius: clamp(radius),
+            },
+        };
+        self
+    }
+
+    /// Sets the maximum number of pixels by which each character is randomly moved up or down.
+    pub fn set_vertical_jitter(&mut self, jitter: u32) -> &mut Self {
+        self.vertical_jitter = jitter;
+        self
+    }
+
     /// Saves the CAPTCHA to a image file.
     ///
     /// The format that is written is determined from the filename's extension. On error `Err` is
     /// returned.
-    pub fn save(&self, p: &Path) -> Result<()> {
-        let i = self.apply_transformations();
+    pub fn save(&self, p: &Path) -> Result<(), Error> {
+        let i = self.apply_transformations()?;
         i.save(p)
     }
 
     /// Sets the characters that should be used when generating a CAPTCHA.
     ///
-    /// Important: The characters have to exist for the current font. You can get all characters
-    /// which are supported by the current font by calling supported_chars().
-    pub fn set_chars(&mut self, c: &[char]) -> &mut Self {
+    /// The characters have to exist for the current font. You can get all characters which are
+    /// supported by the current font by calling supported_chars(). Returns
+    /// `Error::UnknownChar` if a character is not supported.
+    pub fn set_chars(&mut self, c: &[char]) -> Result<&mut Self, Error> {
+        let supported = self.font.chars();
+        if let Some(u) = c.iter().find(|c| !supported.contains(c)) {
+            return Err(Error::UnknownChar(*u));
+        }
         self.use_font_chars = c.to_vec();
-        self
+        Ok(self)
     }
 
-    fn random_char_as_image(&mut self) -> Option<(char, Image)> {
-        match self.use_font_chars.choose(&mut self.rng) {
-            None => None,
-            Some(c) => self.char_as_image(c),
-        }
+    fn random_char_as_image(&mut self) -> Option<Result<(char, Image), Error>> {
+        let c = *self.use_font_chars.choose(&mut self.rng)?;
+        Some(self.char_as_image(&c))
     }
 
-    fn char_as_image(&self, c: &char) -> Option<(char, Image)> {
+    fn char_as_image(&self, c: &char) -> Result<(char, Image), Error> {
         match self.font.png(*c) {
-            None => None,
+            None => Err(Error::UnknownChar(*c)),
             Some(p) => Image::from_png(p).map(|i| (*c, i)),
         }
     }
 
-    fn add_char_to_captcha(&mut self, c: char, i: Image) {
-        let x = self.text_area.right;
-        let y = (self.text_area.bottom + self.text_area.top) / 2 - i.height() / 2;
-        self.img.add_image(x, y, &i);
+    /// Scales and rotates the image of the character `c`.
+    ///
+    /// Returns the transformed image and the y coordinate of the character's baseline within it.
+    fn transform_char(&mut self, c: char, mut i: Image) -> (Image, f32) {
+        let (min, max) = self.scale;
+        let f = if min < max {
+            self.rng.gen_range(min..=max)
+        } else {
+            min
+        };
+        if f != 1.0 {
+            i = i.scale(f);
+        }
+        let baseline = i.height() as f32 - self.font.descent(c) as f32 * f;
 
-        self.text_area.top = min(self.text_area.top, y);
-        self.text_area.right = x + i.width() - 1;
-        self.text_area.bottom = max(self.text_area.bottom, y + i.height() - 1);
+        let (min, max) = self.rotation;
+        if min == 0.0 && max == 0.0 {
+            return (i, baseline);
+        }
+        let degrees = self.rng.gen_range(min..=max);
+        let r = i.rotate(degrees);
+        // the baseline passes through the rotated point below the center of the character
+        let d = (baseline - i.height() as f32 / 2.0) * degrees.to_radians().cos();
+        let baseline = r.height() as f32 / 2.0 + d;
+        (r, baseline)
+    }
+
+    /// Returns the vertical offset of the line along which the text is written at the horizontal
+    /// distance `dx` from the start of the line.
+    fn path_offset(&self, dx: f32) -> f32 {
+        match self.path {
+            TextPath::Straight => 0.0,
+            TextPath::Sine { amplitude, period } => {
+                let t = dx / period.max(1.0) * std::f32::consts::TAU;
+                amplitude * (t + self.path_phase).sin()
+            }
+            TextPath::Arc { radius } => {
+                let r = radius.abs();
+                let d = dx.abs().min(r);
+                radius.signum() * (r - (r * r - d * d).sqrt())
+            }
+        }
+    }
+
+    fn add_char_to_captcha(&mut self, c: char, i: Image) -> Result<(), Error> {
+        let (i, baseline) = self.transform_char(c, i);
+        let x = if self.chars.is_empty() {
+            self.text_area.right as i64
+        } else {
+            let j = self.spacing_jitter as i64;
+            let d = self.spacing as i64 + self.rng.gen_range(-j..=j);
+            self.text_area.right as i64 + d
+        };
+
+        let center = x + i.width() as i64 / 2;
+        let j = self.vertical_jitter as i64;
+        let line = self.line.1 as f32
+            + self.path_offset((center - self.line.0) as f32)
+            + self.rng.gen_range(-j..=j) as f32;
+        let y = match self.align {
+            VerticalAlign::Center => line - i.height() as f32 / 2.0,
+            VerticalAlign::Baseline => line - baseline,
+        }
+        .round() as i64;
+
+        let (x, y) = self.make_room(x, y, i.width(), i.height())?;
+        self.img.darken_image(x, y, &i);
+
+        let g = Geometry::new(x, x + i.width() - 1, y, y + i.height() - 1);
+        self.text_area = if self.chars.is_empty() {
+            g.clone()
+        } else {
+            self.text_area.union(&g)
+        };
+        self.glyphs.push(Some(g));
         self.chars.push(c);
-        // TODO automatically resize if many characters are added
+        Ok(())
     }
 
-    /// Adds a character using the current font.
-    pub fn add_char(&mut self, c: char) -> &mut Self {
-        if let Some((c, i)) = self.char_as_image(&c) {
-            self.add_char_to_captcha(c, i);
+    /// Makes sure that an image of size `w` x `h` fits onto the canvas at the position `x`, `y`.
+    ///
+    /// If the canvas is resized automatically it is enlarged as needed, otherwise the position is
+    /// moved so that the image lies completely on the canvas. Returns the position of the image
+    /// on the resulting canvas or `GeometryError::TooSmall` if the image is larger than a canvas
+    /// which is not resized. A canvas which would have to grow beyond 16384 pixels on a side
+    /// results in `GeometryError::TooLarge`. Enlarging the canvas is charged against the budget.
+    fn make_room(&mut self, x: i64, y: i64, w: u32, h: u32) -> Result<(u32, u32), Error> {
+        if !self.auto_resize {
+            let (cw, ch) = (self.img.width(), self.img.height());
+            if w > cw || h > ch {
+                return Err(GeometryError::TooSmall {
+                    width: cw,
+                    height: ch,
+                }
+                .into());
+            }
+            let x = x.clamp(0, (cw - w) as i64) as u32;
+            let y = y.clamp(0, (ch - h) as i64) as u32;
+            return Ok((x, y));
         }
 
-        self
+        // if the canvas needs to be enlarged add some space for the next characters as well
+        let grow = |n: i64, margin: u32| if n > 0 { n as u64 + margin as u64 } else { 0 };
+        let left = grow(x.saturating_neg(), w);
+        let top = grow(y.saturating_neg(), h);
+        let right = grow(
+            x.saturating_add(w as i64)
+                .saturating_sub(self.img.width() as i64),
+            w,
+        );
+        let bottom = grow(
+            y.saturating_add(h as i64)
+                .saturating_sub(self.img.height() as i64),
+            h,
+        );
+        if (left, top, right, bottom) != (0, 0, 0, 0) {
+            let cw = (self.img.width() as u64).saturating_add(left.saturating_add(right));
+            let ch = (self.img.height() as u64).saturating_add(top.saturating_add(bottom));
+            if cw > MAX_IMAGE_SIZE as u64 || ch > MAX_IMAGE_SIZE as u64 {
+                return Err(GeometryError::TooLarge {
+                    width: cw,
+                    height: ch,
+                }
+                .into());
+            }
+            let (left, top) = (left as u32, top as u32);
+            self.charge(filters::pixels((cw as u32, ch as u32)))?;
+            self.img.expand(left, top, right as u32, bottom as u32);
+            let (w, h) = (self.img.width(), self.img.height());
+            self.translate(left as i64, top as i64, w, h);
+        }
+        Ok(((x + left as i64) as u32, (y + top as i64) as u32))
+    }
+
+    /// Adds a character using the current font.
+    ///
+    /// Returns `Error::UnknownChar` if the character is not supported by the font and
+    /// `GeometryError::TooSmall` if the character is larger than a canvas whose size has been set
+    /// via `set_canvas_size`.
+    pub fn add_char(&mut self, c: char) -> Result<&mut Self, Error> {
+        let (c, i) = self.char_as_image(&c)?;
+        self.add_char_to_captcha(c, i)?;
+        Ok(self)
     }
 
     /// Adds a random character using the current font.
+    ///
+    /// The character is not added if its image cannot be decoded or if it does not fit onto a
+    /// canvas whose size has been set via `set_canvas_size`. Use `try_add_random_char` to get
+    /// the error instead.
     pub fn add_random_char(&mut self) -> &mut Self {
-        if let Some((c, i)) = self.random_char_as_image() {
-            self.add_char_to_captcha(c, i);
-        }
-
+        let _ = self.try_add_random_char();
         self
     }
 
+    /// Adds a random character using the current font.
+    ///
+    /// Returns `Error::Decode` if the image of the character cannot be decoded and
+    /// `GeometryError::TooSmall` if the character is larger than a canvas whose size has been set
+    /// via `set_canvas_size`.
+    pub fn try_add_random_char(&mut self) -> Result<&mut Self, Error> {
+        if let Some(r) = self.random_char_as_image() {
+            let (c, i) = r?;
+            self.add_char_to_captcha(c, i)?;
+        }
+        Ok(self)
+    }
+
     /// Adds a red box to the CAPTCHA representing the area which contains text.
     pub fn add_text_area(&mut self) -> &mut Self {
-        for y in self.text_area.top..self.text_area.bottom {
+        for y in self.text_area.top..=self.text_area.bottom {
             self.img.put_pixel(self.text_area.left, y, Pixl::red());
             self.img.put_pixel(self.text_area.right, y, Pixl::red());
         }
-        for x in self.text_area.left..self.text_area.right {
+        for x in self.text_area.left..=self.text_area.right {
             self.img.put_pixel(x, self.text_area.top, Pixl::red());
             self.img.put_pixel(x, self.text_area.bottom, Pixl::red());
         }
@@ -250,39 +881,105 @@ impl<T: rand::Rng + rand::RngCore> RngCaptcha<T> {
     }
 
     /// Returns the geometry of the area which contains text in the CAPTCHA.
+    ///
+    /// If the text has been cropped entirely by `extract` the area is clamped to the image, i.e.
+    /// it is a line along the edge or the corner pixel which is nearest to the text.
     pub fn text_area(&self) -> Geometry {
         self.text_area.clone()
     }
 
     /// Crops the CAPTCHA to the given geometry.
-    pub fn extract(&mut self, area: &Geometry) -> &mut Self {
+    ///
+    /// The area which contains text and the areas of the characters are moved into the coordinate
+    /// system of the cropped image. If text is cropped its area is clipped to the image. A
+    /// character which is cropped entirely has no area anymore, see `glyphs`.
+    ///
+    /// Returns an error if the given area is empty or not completely within the image or if
+    /// copying the area exceeds the budget, see `set_cost_budget`.
+    pub fn extract(&mut self, area: &Geometry) -> Result<&mut Self, Error> {
         // TODO rename the method
-        // TODO adjust the text area
-        let w = area.right - area.left;
-        let h = area.bottom - area.top;
+        if area.left > area.right || area.top > area.bottom {
+            return Err(GeometryError::InvalidArea(area.clone()).into());
+        }
+        if area.right >= self.img.width() || area.bottom >= self.img.height() {
+            return Err(GeometryError::OutOfBounds {
+                area: area.clone(),
+                width: self.img.width(),
+                height: self.img.height(),
+            }
+            .into());
+        }
+
+        let w = area.right - area.left + 1;
+        let h = area.bottom - area.top + 1;
+        self.charge(filters::pixels((w, h)))?;
         let mut i = Image::new(w, h);
-        for (y, iy) in (area.top..area.bottom).zip(0..h + 1) {
-            for (x, ix) in (area.left..area.right).zip(0..w + 1) {
-                i.put_pixel(ix, iy, self.img.get_pixel(x, y));
+        for (y, iy) in (area.top..=area.bottom).zip(0..h) {
+            for (x, ix) in (area.left..=area.right).zip(0..w) {
+                i.copy_pixel(ix, iy, &self.img, x, y);
             }
         }
         self.img = i;
-        self
+        self.translate(-(area.left as i64), -(area.top as i64), w, h);
+        Ok(self)
     }
 
     /// Crops the CAPTCHA to the given width and height with the text centered withing this
     /// box.
-    pub fn view(&mut self, w: u32, h: u32) -> &mut Self {
-        let mut a = self.text_area();
-        a.left = ((a.right + a.left) / 2).saturating_sub(w / 2);
-        a.right = a.left + w;
-        a.top = ((a.bottom + a.top) / 2).saturating_sub(h / 2);
-        a.bottom = a.top + h;
-        self.extract(&a);
-        // TODO update text area
-        self.text_area = a;
-        // TODO what happens if w or h are too small
-        self
+    ///
+    /// Parts of the view which are outside of the canvas are filled with the background. Returns
+    /// an error if the text does not fit into the view, if the view or the enlarged canvas would
+    /// be larger than 16384 pixels on a side or if enlarging the canvas and copying the view
+    /// exceed the budget, see `set_cost_budget`.
+    pub fn view(&mut self, w: u32, h: u32) -> Result<&mut Self, Error> {
+        if w > MAX_IMAGE_SIZE || h > MAX_IMAGE_SIZE {
+            return Err(GeometryError::TooLarge {
+                width: w as u64,
+                height: h as u64,
+            }
+            .into());
+        }
+        let a = self.text_area();
+        let tw = a.right - a.left + 1;
+        let th = a.bottom - a.top + 1;
+        if tw > w || th > h {
+            return Err(GeometryError::TooSmall {
+                width: w,
+                height: h,
+            }
+            .into());
+        }
+
+        let left = a.left as i64 - (w - tw) as i64 / 2;
+        let top = a.top as i64 - (h - th) as i64 / 2;
+        let pad_left = max(-left, 0) as u64;
+        let pad_top = max(-top, 0) as u64;
+        let pad_right = max(left + w as i64 - self.img.width() as i64, 0) as u64;
+        let pad_bottom = max(top + h as i64 - self.img.height() as i64, 0) as u64;
+        if pad_left + pad_top + pad_right + pad_bottom > 0 {
+            let cw = self.img.width() as u64 + pad_left + pad_right;
+            let ch = self.img.height() as u64 + pad_top + pad_bottom;
+            if cw > MAX_IMAGE_SIZE as u64 || ch > MAX_IMAGE_SIZE as u64 {
+                return Err(GeometryError::TooLarge {
+                    width: cw,
+                    height: ch,
+                }
+                .into());
+            }
+            let (cw, ch) = (cw as u32, ch as u32);
+            let (pad_left, pad_top) = (pad_left as u32, pad_top as u32);
+            // the view is copied by `extract` afterwards, so both copies have to fit
+            self.check_budget(filters::pixels((cw, ch)) + filters::pixels((w, h)))?;
+            self.charge(filters::pixels((cw, ch)))?;
+            self.img
+                .expand(pad_left, pad_top, pad_right as u32, pad_bottom as u32);
+            let (cw, ch) = (self.img.width(), self.img.height());
+            self.translate(pad_left as i64, pad_top as i64, cw, ch);
+        }
+
+        let left = (left + pad_left as i64) as u32;
+        let top = (top + pad_top as i64) as u32;
+        self.extract(&Geometry::new(left, left + w - 1, top, top + h - 1))
     }
 
     /// Returns the characters that have been added to this CAPTCHA.
@@ -290,6 +987,20 @@ impl<T: rand::Rng + rand::RngCore> RngCaptcha<T> {
         self.chars.clone()
     }
 
+    /// Returns the characters that have been added to this CAPTCHA together with the area of
+    /// the image which contains each character.
+    ///
+    /// The areas are kept up to date when the CAPTCHA is cropped or when a filter moves the
+    /// pixels of the image. An area is clipped to the image if its character has been cropped
+    /// partially and it is `None` if its character has been cropped entirely.
+    pub fn glyphs(&self) -> Vec<(char, Option<Geometry>)> {
+        self.chars
+            .iter()
+            .cloned()
+            .zip(self.glyphs.iter().cloned())
+            .collect()
+    }
+
     /// Returns the characters that have been added to this CAPTCHA collected into a string.
     pub fn chars_as_string(&self) -> String {
         self.chars.iter().collect()
@@ -303,35 +1014,63 @@ impl<T: rand::Rng + rand::RngCore> RngCaptcha<T> {
         self
     }
 
-    fn apply_transformations(&self) -> Image {
+    /// Adds the given number of random characters to the CAPTCHA using the current font.
+    ///
+    /// Stops at the first character which cannot be added and returns the error, see
+    /// `try_add_random_char`.
+    pub fn try_add_random_chars(&mut self, n: u32) -> Result<&mut Self, Error> {
+        for _ in 0..n {
+            self.try_add_random_char()?;
+        }
+        Ok(self)
+    }
+
+    fn apply_transformations(&self) -> Result<Image, Error> {
         let mut i = self.img.clone();
-        if self.color.is_some() {
-            i.set_color(&self.color.unwrap());
+        if let Some(color) = self.color {
+            i.set_color(&color);
+        }
+        if !self.backgrounds.is_empty() {
+            let (w, h) = (i.width(), i.height());
+            let large: Vec<&Image> = self
+                .backgrounds
+                .iter()
+                .filter(|b| b.width() >= w && b.height() >= h)
+                .collect();
+            if large.is_empty() {
+                return Err(Error::BackgroundTooSmall {
+                    width: w,
+                    height: h,
+                });
+            }
+            let [n, fx, fy] = self.background_choice;
+            let b = large[min((n * large.len() as f32) as usize, large.len() - 1)];
+            let left = (fx * (b.width() - w + 1) as f32) as u32;
+            let top = (fy * (b.height() - h + 1) as f32) as u32;
+            let left = min(left, b.width() - w);
+            let top = min(top, b.height() - h);
+            i.fill_under(|x, y| b.get_pixel(left + x, top + y));
         }
-        i
+        Ok(i)
     }
 
     /// Returns the CAPTCHA as a png image.
-    ///
-    /// Returns `None` on error.
-    pub fn as_png(&self) -> Option<Vec<u8>> {
-        let i = self.apply_transformations();
+    pub fn as_png(&self) -> Result<Vec<u8>, Error> {
+        let i = self.apply_transformations()?;
         i.as_png()
     }
 
-    pub fn as_base64(&self) -> Option<String> {
-        self.as_png().map(base64::encode)
+    /// Returns the CAPTCHA as a base64 encoded png image.
+    pub fn as_base64(&self) -> Result<String, Error> {
+        use base64::Engine;
+        self.as_png()
+            .map(|p| base64::engine::general_purpose::STANDARD.encode(p))
     }
 
     /// Returns a tuple which contains the characters that have been added to this CAPTCHA
     /// as a string and the image encoded as a PNG.
-    ///
-    /// Returns `None` on error.
-    pub fn as_tuple(&self) -> Option<(String, Vec<u8>)> {
-        match self.as_png() {
-            None => None,
-            Some(p) => Some((self.chars_as_string(), p)),
-        }
+    pub fn as_tuple(&self) -> Result<(String, Vec<u8>), Error> {
+        self.as_png().map(|p| (self.chars_as_string(), p))
     }
 
     /// Returns the supported characters of the current font.
@@ -342,12 +1081,54 @@ impl<T: rand::Rng + rand::RngCore> RngCaptcha<T> {
 
 #[cfg(test)]
 mod tests {
-    use filters::{Grid, Noise};
+    use filters::{
+        Background, BoxBlur, ColorInvert, Cow, Distractors, Dots, Elastic, Filter, GaussianBlur,
+        Grid, Lens, Noise, NoiseMode, Pattern, Radial, RandomLine, Ripple, Rotate, SerdeColor,
+        StrikeThrough, Swirl, Warp, Wave,
+    };
     use fonts::Default;
-    use Captcha;
+    use images::{Image, Pixl};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use {
+        Captcha, Error, Geometry, GeometryError, RngCaptcha, TextPath, VerticalAlign,
+        MAX_IMAGE_SIZE,
+    };
 
     use std::path::Path;
 
+    /// The color of a pixel of the view before and after the CAPTCHA has been changed.
+    struct PixelDiff {
+        x: u32,
+        y: u32,
+        before: [u8; 4],
+        after: [u8; 4],
+    }
+
+    /// Adds `n` random characters to a CAPTCHA whose generator is seeded with `seed` and crops
+    /// it to 220x120. Then `f` changes the CAPTCHA and returns the resulting image, which is
+    /// compared with the view pixel by pixel.
+    fn diff<F: FnOnce(&mut RngCaptcha<StdRng>) -> Image>(
+        seed: u64,
+        n: u32,
+        f: F,
+    ) -> (RngCaptcha<StdRng>, Vec<PixelDiff>) {
+        let mut c = RngCaptcha::from_rng(StdRng::seed_from_u64(seed));
+        c.add_random_chars(n).view(220, 120).expect("view failed");
+        let before = c.img.clone();
+        let after = f(&mut c);
+        let pixels = (0..120)
+            .flat_map(|y| (0..220).map(move |x| (x, y)))
+            .map(|(x, y)| PixelDiff {
+                x,
+                y,
+                before: before.get_pixel(x, y).rgba(),
+                after: after.get_pixel(x, y).rgba(),
+            })
+            .collect();
+        (c, pixels)
+    }
+
     #[test]
     fn it_works() {
         let mut c = Captcha::new();
@@ -363,16 +1144,774 @@ mod tests {
 
         let a = c.text_area();
         c.extract(&a)
+            .expect("extract failed")
             .save(Path::new("/tmp/captcha.png"))
             .expect("save failed");
         c.as_png().expect("no png");
     }
 
+    #[test]
+    fn rotated_char_text_area() {
+        let mut c = Captcha::new();
+        c.add_char('l').expect("add_char failed");
+        let upright = c.text_area();
+
+        let mut c = Captcha::new();
+        c.set_rotation(90.0, 90.0)
+            .add_char('l')
+            .expect("add_char failed");
+        let rotated = c.text_area();
+
+        assert_eq!(rotated.right - rotated.left, upright.bottom - upright.top);
+    }
+
+    #[test]
+    fn scaled_char_text_area() {
+        let mut c = Captcha::new();
+        c.add_char('l').expect("add_char failed");
+        let native = c.text_area();
+
+        let mut c = Captcha::new();
+        c.set_scale(2.0).add_char('l').expect("add_char failed");
+        let scaled = c.text_area();
+
+        assert_eq!(
+            scaled.bottom - scaled.top + 1,
+            2 * (native.bottom - native.top + 1)
+        );
+    }
+
+    #[test]
+    fn out_of_range_transformations() {
+        let mut c = Captcha::new();
+        c.set_rotation(f32::NAN, 0.0)
+            .add_char('l')
+            .expect("add_char failed");
+        c.set_rotation(-f32::INFINITY, f32::INFINITY)
+            .set_scale_range(-3e38, 3e38)
+            .add_char('l')
+            .expect("add_char failed");
+        assert_eq!((c.rotation, c.scale), ((-360.0, 360.0), (0.0, 64.0)));
+        c.set_scale(f32::NAN)
+            .add_char('l')
+            .expect("add_char failed");
+        assert_eq!(c.scale, (1.0, 1.0));
+        assert_eq!(c.chars().len(), 3);
+    }
+
+    #[test]
+    fn canvas_grows_with_text() {
+        let mut c = Captcha::new();
+        c.set_scale(2.0).add_random_chars(12);
+        let a = c.text_area();
+        assert!(a.right < c.img.width() && a.bottom < c.img.height());
+        assert!(a.right - a.left > 400);
+    }
+
+    #[test]
+    fn fixed_canvas_keeps_chars_on_canvas() {
+        let mut c = RngCaptcha::from_rng(StdRng::seed_from_u64(1));
+        c.set_canvas_size(100, 60)
+            .expect("canvas failed")
+            .set_spacing(80)
+            .add_random_chars(4);
+        assert_eq!(c.chars().len(), 4);
+        for (_, g) in c.glyphs() {
+            let g = g.expect("glyph was cropped");
+            assert!(g.left <= g.right && g.right < 100);
+            assert!(g.top <= g.bottom && g.bottom < 60);
+        }
+
+        let mut c = Captcha::new();
+        c.set_canvas_size(10, 10).expect("canvas failed");
+        assert!(matches!(
+            c.add_char('a'),
+            Err(Error::Geometry(GeometryError::TooSmall {
+                width: 10,
+                height: 10
+            }))
+        ));
+        c.add_random_chars(3);
+        assert!(c.chars().is_empty() && c.glyphs().is_empty());
+    }
+
+    #[test]
+    fn text_path_is_clamped() {
+        let mut c = RngCaptcha::from_rng(StdRng::seed_from_u64(2));
+        c.set_text_path(TextPath::Sine {
+            amplitude: 1e30,
+            period: 10.0,
+        });
+        assert_eq!(
+            c.path,
+            TextPath::Sine {
+                amplitude: 16384.0,
+                period: 10.0
+            }
+        );
+        c.set_text_path(TextPath::Arc { radius: f32::NAN });
+        assert_eq!(c.path, TextPath::Arc { radius: 0.0 });
+        c.add_char('a').expect("add_char failed");
+
+        // positions which would need a huge canvas are refused
+        for (x, y) in [(i64::MAX, 0), (0, i64::MIN), (-100_000, 0)] {
+            assert!(matches!(
+                c.make_room(x, y, 10, 10),
+                Err(Error::Geometry(GeometryError::TooLarge { .. }))
+            ));
+        }
+    }
+
+    #[test]
+    fn baseline_alignment() {
+        let mut c = Captcha::new();
+        c.set_vertical_align(VerticalAlign::Baseline);
+        for ch in &['a', 'h', 'g'] {
+            c.add_char(*ch).expect("add_char failed");
+        }
+        let glyphs: Vec<Geometry> = c.glyphs().into_iter().filter_map(|(_, g)| g).collect();
+        let (a, h, g) = (&glyphs[0], &glyphs[1], &glyphs[2]);
+        assert_eq!(a.bottom, h.bottom);
+        assert!(g.bottom > a.bottom);
+        assert_eq!(c.text_area().bottom, g.bottom);
+        assert_eq!(c.text_area().top, h.top);
+    }
+
+    #[test]
+    fn glyphs_follow_view_and_wave() {
+        let mut c = Captcha::new();
+        c.add_random_chars(4)
+            .apply_filter(Wave::new(2.0, 20.0))
+            .expect("wave failed")
+            .view(220, 120)
+            .expect("view failed");
+
+        let glyphs = c.glyphs();
+        assert_eq!(glyphs.len(), 4);
+        for (i, (ch, g)) in glyphs.iter().enumerate() {
+            assert_eq!(*ch, c.chars()[i]);
+            let g = g.as_ref().expect("glyph was cropped");
+            assert!(g.left <= g.right && g.right < 220);
+            assert!(g.top <= g.bottom && g.bottom < 120);
+        }
+        let s = serde_json::to_string(&glyphs).expect("serialize failed");
+        assert!(s.starts_with("[["));
+    }
+
+    #[test]
+    fn text_area_after_view() {
+        let mut c = Captcha::new();
+        c.add_random_chars(4);
+        let before = c.text_area();
+        c.view(220, 120).expect("view failed");
+        let after = c.text_area();
+
+        assert_eq!(after.right - after.left, before.right - before.left);
+        assert_eq!(after.bottom - after.top, before.bottom - before.top);
+        assert!(after.right < 220 && after.bottom < 120);
+
+        let a = Geometry::new(after.left + 5, after.right, after.top, after.bottom);
+        c.extract(&a).expect("extract failed");
+        let clipped = c.text_area();
+        assert_eq!(clipped.left, 0);
+        assert_eq!(clipped.right, after.right - after.left - 5);
+        assert_eq!(c.img.width(), clipped.right + 1);
+    }
+
+    #[test]
+    fn extract_crops_glyphs() {
+        let mut c = RngCaptcha::from_rng(StdRng::seed_from_u64(5));
+        c.add_random_chars(3);
+        let glyphs: Vec<Geometry> = c.glyphs().into_iter().filter_map(|(_, g)| g).collect();
+        let (first, last) = (glyphs[0].clone(), glyphs[2].clone());
+        let h = c.img.height();
+
+        // keeps the first character and the left half of the second one
+        let right = (glyphs[1].left + glyphs[1].right) / 2;
+        c.extract(&Geometry::new(first.left, right, 0, h - 1))
+            .expect("extract failed");
+        let glyphs = c.glyphs();
+        assert_eq!(glyphs.len(), 3);
+        assert_eq!(glyphs[0].1.as_ref().map(|g| g.left), Some(0));
+        assert_eq!(
+            glyphs[1].1.as_ref().map(|g| g.right),
+            Some(right - first.left)
+        );
+        assert_eq!(glyphs[2].1, None);
+
+        // the text area of text which has been cropped entirely is clamped to the image
+        let mut c = RngCaptcha::from_rng(StdRng::seed_from_u64(5));
+        c.add_random_chars(3);
+        let w = c.img.width();
+        assert!(last.right + 1 < w);
+        c.extract(&Geometry::new(last.right + 1, w - 1, 0, h - 1))
+            .expect("extract failed");
+        assert!(c.glyphs().iter().all(|(_, g)| g.is_none()));
+        let a = c.text_area();
+        assert_eq!((a.left, a.right), (0, 0));
+    }
+
+    #[test]
+    fn unknown_chars() {
+        let mut c = Captcha::new();
+        assert!(matches!(c.add_char('?'), Err(Error::UnknownChar('?'))));
+        assert!(c.set_chars(&['a', '!']).is_err());
+        assert!(c.set_chars(&['a', 'b']).is_ok());
+    }
+
+    #[test]
+    fn seeded_captchas_are_reproducible() {
+        let png = |seed| {
+            let mut c = RngCaptcha::from_rng(StdRng::seed_from_u64(seed));
+            c.add_random_chars(5)
+                .apply_filter(Noise::new(0.2))
+                .expect("noise failed")
+                .view(220, 120)
+                .expect("view failed")
+                .apply_filter(Dots::new(4))
+                .expect("dots failed")
+                .apply_filter(Cow::new())
+                .expect("cow failed")
+                .apply_filter(RandomLine::new())
+                .expect("random line failed");
+            c.as_png().expect("no png")
+        };
+        assert_eq!(png(7), png(7));
+        assert_ne!(png(7), png(8));
+    }
+
+    #[test]
+    fn rotate_moves_text_area() {
+        let mut c = Captcha::new();
+        c.add_random_chars(4).view(220, 120).expect("view failed");
+        let a = c.text_area();
+        c.apply_filter(Rotate::new(180.0)).expect("rotate failed");
+        let r = c.text_area();
+        assert!((r.left as i64 - (219 - a.right) as i64).abs() <= 1);
+        assert!((r.bottom as i64 - (119 - a.top) as i64).abs() <= 1);
+        assert_eq!(c.img.width(), 220);
+
+        let mut c = Captcha::new();
+        c.add_random_chars(4).view(220, 120).expect("view failed");
+        let before = c.as_png().expect("no png");
+        c.apply_filter(Rotate::new(0.0)).expect("rotate failed");
+        assert_eq!(before, c.as_png().expect("no png"));
+        // characters which are rotated off the canvas lose their area instead of being clamped
+        let mut c = RngCaptcha::from_rng(StdRng::seed_from_u64(0));
+        c.set_spacing(20)
+            .add_random_chars(5)
+            .view(300, 120)
+            .expect("view failed");
+        c.apply_filter(Rotate::new(90.0)).expect("rotate failed");
+        let glyphs = c.glyphs();
+        assert!(glyphs.iter().any(|(_, g)| g.is_none()));
+        for g in glyphs.iter().filter_map(|(_, g)| g.as_ref()) {
+            assert!(g.right - g.left > 5 && g.bottom - g.top > 5);
+        }
+    }
+
+    #[test]
+    fn warp_keeps_glyphs_on_canvas() {
+        let mut c = RngCaptcha::from_rng(StdRng::seed_from_u64(1));
+        c.add_random_chars(4)
+            .view(220, 120)
+            .expect("view failed")
+            .apply_filter(Warp::perspective([(0.0, 0.0); 4]).jitter(0.2))
+            .expect("warp failed");
+        let a = c.text_area();
+        assert!(a.left > 0 || a.right < 219);
+        for (_, g) in c.glyphs() {
+            let g = g.expect("glyph was cropped");
+            assert!(g.left >= a.left && g.right <= a.right);
+            assert!(g.top >= a.top && g.bottom <= a.bottom);
+        }
+    }
+
+    #[test]
+    fn elastic_distorts_text() {
+        let mut c = Captcha::new();
+        c.add_random_chars(4).view(220, 120).expect("view failed");
+        let before = c.as_png().expect("no png");
+        let a = c.text_area();
+        c.apply_filter(Elastic::new(5.0, 8.0))
+            .expect("elastic failed");
+        assert_ne!(before, c.as_png().expect("no png"));
+        let e = c.text_area();
+        assert!(e.left + 5 >= a.left && e.right <= a.right + 5);
+    }
+
+    #[test]
+    fn translate_saturates() {
+        let g = Geometry::new(10, 20, 10, 20);
+        assert_eq!(
+            g.translate(i64::MAX, i64::MIN, 100, 50),
+            Geometry::new(99, 99, 0, 0)
+        );
+        assert_eq!(g.translate_within(i64::MAX, 0, 100, 50), None);
+        assert_eq!(g.translate_within(0, i64::MIN, 100, 50), None);
+    }
+
+    #[test]
+    fn radial_distortions() {
+        let filters: Vec<Box<dyn Filter>> = vec![
+            Box::new(Swirl::new(Radial::new(120.0).radius(0.6))),
+            Box::new(Ripple::new(
+                Radial::new(3.0).center_range((0.3, 0.7), (0.5, 0.5)),
+                10.0,
+            )),
+            Box::new(Lens::new(Radial::new(-0.3).radius_range(0.3, 0.5))),
+        ];
+        for f in &filters {
+            let mut c = RngCaptcha::from_rng(StdRng::seed_from_u64(5));
+            c.add_random_chars(4).view(220, 120).expect("view failed");
+            let before = c.as_png().expect("no png");
+            c.apply_filter_dyn(f.as_ref()).expect("filter failed");
+            assert_ne!(before, c.as_png().expect("no png"));
+            let a = c.text_area();
+            assert!(a.right < 220 && a.bottom < 120);
+        }
+    }
+
+    #[test]
+    fn wave_has_no_holes() {
+        for wave in [
+            Wave::new(2.0, 6.0),
+            Wave::new(1.0, 6.0).vertical().random_phase(),
+            Wave::new(2.0, 4.0).harmonic(5.0, 2.0).both().phase(1.0),
+        ] {
+            let mut c = Captcha::new();
+            c.set_canvas_size(200, 100)
+                .expect("canvas failed")
+                .apply_filter(ColorInvert::new())
+                .expect("invert failed")
+                .apply_filter(wave)
+                .expect("wave failed");
+            for y in 7..93 {
+                for x in 7..193 {
+                    assert_eq!(c.img.get_pixel(x, y).rgba()[0], 0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn wave_configs_are_compatible() {
+        let f: Box<dyn Filter> =
+            serde_json::from_str(r#"{"filter": "Wave", "f": 2.0, "amp": 20.0, "d": "vertical"}"#)
+                .expect("deserialize failed");
+        let w = Wave::new(2.0, 20.0).vertical();
+        assert_eq!(
+            serde_json::to_value(&f).expect("serialize failed"),
+            serde_json::to_value(&w as &dyn Filter).expect("serialize failed")
+        );
+    }
+
+    #[test]
+    fn strike_through_stays_near_text() {
+        let (c, pixels) = diff(9, 5, |c| {
+            c.apply_filter(StrikeThrough::new(2).thickness(4.0).antialias(true))
+                .expect("strike through failed");
+            c.img.clone()
+        });
+
+        let a = c.text_area();
+        let changed: Vec<&PixelDiff> = pixels.iter().filter(|p| p.after != p.before).collect();
+        for p in &changed {
+            assert!(p.x + 3 >= a.left && p.x <= a.right + 3);
+            assert!(p.y + 3 >= a.top && p.y <= a.bottom + 3);
+        }
+        let gray = pixels.iter().filter(|p| p.after[0] > 0 && p.after[0] < 255);
+        assert!(!changed.is_empty() && gray.count() > 0);
+    }
+
+    #[test]
+    fn distractors_stay_behind_text() {
+        let mut text = None;
+        let (c, pixels) = diff(6, 5, |c| {
+            text = Some((c.chars_as_string(), c.text_area()));
+            c.apply_filter(Distractors::new(12).color(SerdeColor::new(150, 150, 150)))
+                .expect("distractors failed");
+            c.img.clone()
+        });
+
+        for p in &pixels {
+            assert!((0..3).all(|k| p.after[k] <= p.before[k]));
+            // distractors are lighter than the color of the text
+            assert!(p.after == p.before || p.after[0] >= 150);
+        }
+        assert!(pixels.iter().any(|p| p.after != p.before));
+        assert_eq!(Some((c.chars_as_string(), c.text_area())), text);
+    }
+
+    #[test]
+    fn distractors_fit_large_glyphs() {
+        use base64::Engine;
+        let mut glyph = Image::new(1000, 1000);
+        for y in 0..1000 {
+            for x in 0..1000 {
+                glyph.put_pixel(x, y, Pixl::black());
+            }
+        }
+        let png = glyph.as_png().expect("encoding failed");
+        let json =
+            serde_json::json!({ "a": base64::engine::general_purpose::STANDARD.encode(png) });
+        let font = Default::from_json(&json.to_string()).expect("invalid font");
+
+        let distractors = Distractors::new(4)
+            .color(SerdeColor::new(0, 0, 0))
+            .alpha(1.0);
+        let mut c = RngCaptcha::from_rng(StdRng::seed_from_u64(2));
+        c.set_cost_budget(distractors.cost((100, 60)) + 6000)
+            .set_canvas_size(100, 60)
+            .expect("canvas failed")
+            .set_font(font)
+            .apply_filter(distractors)
+            .expect("distractors failed");
+        let pixels: Vec<[u8; 4]> = (0..60)
+            .flat_map(|y| (0..100).map(move |x| (x, y)))
+            .map(|(x, y)| c.img.get_pixel(x, y).rgba())
+            .collect();
+        // the glyphs are shrunk to fit onto the image instead of covering it
+        assert!(pixels.contains(&[0, 0, 0, 255]));
+        assert!(pixels.contains(&[255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn backgrounds_stay_under_text() {
+        let red = SerdeColor::new(255, 0, 0);
+        let blue = SerdeColor::new(0, 0, 255);
+        let patterns = vec![
+            Pattern::LinearGradient {
+                from: red.clone(),
+                to: blue.clone(),
+                angle: 30.0,
+            },
+            Pattern::RadialGradient {
+                inner: red.clone(),
+                outer: blue.clone(),
+                center: (0.3, 0.6),
+            },
+            Pattern::Stripes {
+                a: red.clone(),
+                b: blue.clone(),
+                width: 6,
+                angle: -45.0,
+            },
+            Pattern::Checkerboard {
+                a: red.clone(),
+                b: blue.clone(),
+                size: 8,
+            },
+            Pattern::Noise {
+                from: red.clone(),
+                to: blue.clone(),
+                scale: 40.0,
+                octaves: 3,
+            },
+        ];
+
+        for pattern in patterns {
+            let (_, pixels) = diff(3, 4, |c| {
+                c.apply_filter(Background::new(pattern))
+                    .expect("background failed");
+                c.img.clone()
+            });
+
+            let (mut red, mut blue) = (0, 0);
+            for p in &pixels {
+                if p.before[..3] == [0, 0, 0] {
+                    assert_eq!(p.after, p.before);
+                }
+                if p.before[..3] == [255, 255, 255] {
+                    assert_eq!(p.after[1], 0);
+                    red += (p.after[0] > p.after[2]) as u32;
+                    blue += (p.after[2] > p.after[0]) as u32;
+                }
+            }
+            assert!(red > 0 && blue > 0);
+        }
+    }
+
+    #[test]
+    fn color_keeps_background() {
+        let (_, pixels) = diff(3, 4, |c| {
+            c.apply_filter(Background::new(Pattern::LinearGradient {
+                from: SerdeColor::new(0, 120, 255),
+                to: SerdeColor::new(0, 255, 120),
+                angle: 0.0,
+            }))
+            .expect("background failed")
+            .set_color([200, 0, 0, 255]);
+            c.apply_transformations().expect("conversion failed")
+        });
+
+        for p in &pixels {
+            match p.before {
+                [0, 0, 0, _] => assert_eq!(p.after, [200, 0, 0, 255]),
+                [255, 255, 255, _] => {
+                    assert!(p.after[0] == 0 && p.after[1] >= 120 && p.after[2] >= 120)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn color_keeps_gray_shapes() {
+        let (_, pixels) = diff(3, 4, |c| {
+            c.apply_filter(Background::new(Pattern::Checkerboard {
+                a: SerdeColor::new(200, 200, 200),
+                b: SerdeColor::new(220, 220, 220),
+                size: 8,
+            }))
+            .expect("background failed")
+            .apply_filter(Distractors::new(12))
+            .expect("distractors failed")
+            .apply_filter(Noise::new(0.2).mode(NoiseMode::Gaussian { sigma: 40.0 }))
+            .expect("noise failed")
+            .set_color([200, 0, 0, 255]);
+            c.apply_transformations().expect("conversion failed")
+        });
+
+        let mut gray = 0;
+        for p in pixels.iter().filter(|p| p.before == [255, 255, 255, 255]) {
+            // shapes which have been drawn by filters are not tinted with the color of the text
+            assert!(p.after[0] == p.after[1] && p.after[1] == p.after[2]);
+            gray += (p.after[0] < 255) as u32;
+        }
+        assert!(gray > 0);
+        assert!(pixels
+            .iter()
+            .any(|p| p.after[0] > p.after[1] && p.after[1] == p.after[2]));
+    }
+
+    #[test]
+    fn color_covers_antialiased_text() {
+        let mut c = RngCaptcha::from_rng(StdRng::seed_from_u64(3));
+        c.add_random_chars(5)
+            .apply_filter(Wave::new(2.0, 10.0))
+            .expect("wave failed")
+            .view(220, 120)
+            .expect("view failed")
+            .set_color([200, 0, 0, 255]);
+        let colored = c.apply_transformations().expect("conversion failed");
+
+        let mut gray = 0;
+        for y in 0..120 {
+            for x in 0..220 {
+                let [r, g, b, _] = c.img.get_pixel(x, y).rgba();
+                let p = colored.get_pixel(x, y).rgba();
+                if r == g && g == b && r < 255 {
+                    // no text pixel keeps a gray halo
+                    assert!(p[0] > p[1] && p[1] == g && p[2] == b);
+                    gray += (r > 0) as u32;
+                }
+            }
+        }
+        assert!(gray > 0);
+    }
+
+    #[test]
+    fn blur_smooths_area() {
+        let (_, pixels) = diff(2, 4, |c| {
+            c.apply_filter(GaussianBlur::new(1.5).area(Geometry::new(0, 109, 0, 119)))
+                .expect("gaussian blur failed");
+            c.img.clone()
+        });
+
+        for p in pixels.iter().filter(|p| p.x >= 110) {
+            assert_eq!(p.after, p.before);
+        }
+        assert!(pixels.iter().any(|p| p.after[0] > 0 && p.after[0] < 255));
+
+        let mut c = Captcha::new();
+        c.set_canvas_size(50, 50)
+            .expect("canvas failed")
+            .apply_filter(BoxBlur::new(2))
+            .expect("box blur failed");
+        assert_eq!(c.img.get_pixel(25, 25).rgba(), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn noise_modes() {
+        let red = SerdeColor::new(255, 0, 0);
+        let blue = SerdeColor::new(0, 0, 255);
+        let pixels = |mode: NoiseMode| {
+            let mut c = RngCaptcha::from_rng(StdRng::seed_from_u64(4));
+            c.set_canvas_size(60, 40)
+                .expect("canvas failed")
+                .apply_filter(Noise::new(0.5).mode(mode))
+                .expect("noise failed");
+            let mut v = vec![];
+            for y in 0..40 {
+                for x in 0..60 {
+                    v.push(c.img.get_pixel(x, y).rgba());
+                }
+            }
+            v
+        };
+
+        let v = pixels(NoiseMode::SaltAndPepper {
+            salt: red.clone(),
+            pepper: blue.clone(),
+        });
+        assert!(v.iter().all(|p| p[1] == 0 || *p == [255, 255, 255, 255]));
+        assert!(v.contains(&[255, 0, 0, 255]) && v.contains(&[0, 0, 255, 255]));
+
+        let v = pixels(NoiseMode::Gaussian { sigma: 40.0 });
+        assert!(v.iter().any(|p| p[0] > 0 && p[0] < 255 && p[0] == p[2]));
+
+        let v = pixels(NoiseMode::RandomHue);
+        assert!(v.iter().any(|p| p[0] != p[1] || p[1] != p[2]));
+
+        let v = pixels(NoiseMode::Clusters {
+            min_radius: 1,
+            max_radius: 3,
+            color: blue,
+        });
+        assert!(v.contains(&[0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn background_images() {
+        let mut large = Image::new(300, 200);
+        for y in 0..200 {
+            for x in 0..300 {
+                // the right half is transparent
+                let a = if x < 150 { 255 } else { 0 };
+                large.put_pixel(x, y, Pixl::new_with_alpha(200, 100, 50, a));
+            }
+        }
+        let pngs = vec![
+            Image::new(10, 10).as_png().expect("encoding failed"),
+            large.as_png().expect("encoding failed"),
+        ];
+
+        let (mut c, pixels) = diff(8, 5, |c| {
+            c.set_background_images(&pngs)
+                .expect("invalid background images");
+            let png = c.as_png().expect("conversion failed");
+            assert_eq!(c.as_png().expect("conversion failed"), png);
+            Image::from_png(png).expect("invalid png")
+        });
+
+        let (mut background, mut white) = (0, 0);
+        for p in &pixels {
+            if p.before == [0, 0, 0, 255] {
+                assert_eq!(p.after, p.before);
+            }
+            if p.before == [255, 255, 255, 255] {
+                assert!(p.after == [200, 100, 50, 255] || p.after == p.before);
+                background += (p.after != p.before) as u32;
+                white += (p.after == p.before) as u32;
+            }
+        }
+        assert!(background > 0 && white > 0);
+
+        c.set_background_images(&pngs[..1])
+            .expect("invalid background images");
+        assert!(matches!(
+            c.as_png(),
+            Err(Error::BackgroundTooSmall {
+                width: 220,
+                height: 120
+            })
+        ));
+        assert!(matches!(
+            c.set_background_images(&[vec![1, 2, 3]]),
+            Err(Error::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn cost_budget() {
+        let mut c = Captcha::new();
+        // creating the canvas is charged
+        c.set_cost_budget(17_000)
+            .set_canvas_size(100, 50)
+            .expect("canvas failed")
+            .apply_filter(Noise::new(0.1))
+            .expect("noise failed");
+        assert_eq!(c.cost(), 10_000);
+        assert!(matches!(
+            c.apply_filter(Wave::new(2.0, 10.0)),
+            Err(Error::BudgetExceeded {
+                cost: 30150,
+                budget: 17000
+            })
+        ));
+        assert_eq!(c.cost(), 10_000);
+        assert!(c.apply_filter(Noise::new(0.1)).is_ok());
+
+        // copying the view is charged as well
+        c.view(40, 20).expect("view failed");
+        assert_eq!(c.cost(), 15800);
+        // the canvas has to be enlarged for a larger view
+        assert!(matches!(
+            c.view(60, 20),
+            Err(Error::BudgetExceeded {
+                cost: 18200,
+                budget: 17000
+            })
+        ));
+        assert_eq!((c.cost(), c.img.width()), (15800, 40));
+
+        // characters which do not fit make the canvas grow
+        let mut c = RngCaptcha::from_rng(StdRng::seed_from_u64(0));
+        c.set_spacing(400).set_cost_budget(100_000);
+        assert!(matches!(
+            c.try_add_random_chars(2),
+            Err(Error::BudgetExceeded { .. })
+        ));
+        assert_eq!((c.chars().len(), c.cost()), (1, 0));
+    }
+
     #[test]
     fn image_size() {
         let mut c = Captcha::new();
-        c.view(8, 16);
+        c.view(8, 16).expect("view failed");
         assert_eq!(&c.img.width(), &8);
         assert_eq!(&c.img.height(), &16);
     }
+
+    #[test]
+    fn view_errors() {
+        let mut c = Captcha::new();
+        c.add_random_chars(3);
+        assert!(matches!(
+            c.view(10, 10),
+            Err(Error::Geometry(GeometryError::TooSmall { .. }))
+        ));
+        assert!(c.view(0, 0).is_err());
+        for size in [100_000, u32::MAX] {
+            assert!(matches!(
+                c.view(size, size),
+                Err(Error::Geometry(GeometryError::TooLarge { .. }))
+            ));
+        }
+        for size in [20_000, u32::MAX] {
+            assert!(matches!(
+                c.set_canvas_size(size, 100),
+                Err(Error::Geometry(GeometryError::TooLarge { .. }))
+            ));
+        }
+        assert!(c.set_canvas_size(MAX_IMAGE_SIZE, 100).is_ok());
+
+        let mut c = Captcha::new();
+        let a = Geometry::new(0, 1000, 0, 10);
+        assert!(c.extract(&a).is_err());
+    }
+
+    #[test]
+    fn view_is_padded() {
+        let mut c = Captcha::new();
+        c.set_canvas_size(100, 60)
+            .expect("canvas failed")
+            .add_random_chars(2);
+        let a = c.text_area();
+        c.view(300, 200).expect("view failed");
+        assert_eq!(c.img.width(), 300);
+        assert_eq!(c.img.height(), 200);
+        assert_eq!(c.text_area().right - c.text_area().left, a.right - a.left);
+    }
 }
@@ -13,7 +13,7 @@
 //! gen(Difficulty::Easy).as_png();
 //! # }
 //! ```
-use filters::{Cow, Dots, Grid, Noise, Wave};
+use filters::{Cow, Dots, Filter, Grid, Noise, Wave};
 use rand::{thread_rng, Rng};
 use {Captcha, Geometry};
 
@@ -21,6 +21,7 @@ const WIDTH: u32 = 220;
 const HEIGHT: u32 = 120;
 
 /// The difficulty of a CAPTCHA.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Difficulty {
     Easy,
     Medium,
@@ -28,14 +29,14 @@ pub enum Difficulty {
 }
 
 /// Names of predefined CAPTCHAs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CaptchaName {
     Amelia,
     Lucy,
     Mila,
 }
 
-static CAPTCHA_FUNCTIONS: &[fn(Difficulty) -> Captcha] =
-    &[captcha_amelia, captcha_lucy, captcha_mila];
+static CAPTCHA_NAMES: &[CaptchaName] = &[CaptchaName::Amelia, CaptchaName::Lucy, CaptchaName::Mila];
 
 /// Creates a random CAPTCHA with the given difficulty.
 ///
@@ -44,8 +45,8 @@ static CAPTCHA_FUNCTIONS: &[fn(Difficulty) -> Captcha] =
 ///
 /// If you need more flexibility please have a look at [`Captcha`](../struct.Captcha.html).
 pub fn gen(d: Difficulty) -> Captcha {
-    let n = thread_rng().gen::<usize>() % CAPTCHA_FUNCTIONS.len();
-    CAPTCHA_FUNCTIONS[n](d)
+    let n = thread_rng().gen::<usize>() % CAPTCHA_NAMES.len();
+    by_name(d, CAPTCHA_NAMES[n])
 }
 
 // TODO document easy/medium/hard
@@ -111,103 +112,109 @@ pub fn gen(d: Difficulty) -> Captcha {
 /// </div>
 ///
 pub fn by_name(d: Difficulty, t: CaptchaName) -> Captcha {
-    match t {
-        CaptchaName::Amelia => captcha_amelia(d),
-        CaptchaName::Lucy => captcha_lucy(d),
-        CaptchaName::Mila => captcha_mila(d),
+    let (before, after) = filters(d, t);
+    let mut c = captcha_with_chars();
+    for f in &before {
+        c.apply_filter_dyn(f.as_ref()).expect("filter failed");
+    }
+    c.view(WIDTH, HEIGHT).expect("view failed");
+    for f in &after {
+        c.apply_filter_dyn(f.as_ref()).expect("filter failed");
     }
+    c
 }
 
 // -------------------------------------------
 
+type Filters = (Vec<Box<dyn Filter>>, Vec<Box<dyn Filter>>);
+
 fn rnd() -> u32 {
     thread_rng().gen_range(4..7)
 }
 
-fn captcha_amelia(d: Difficulty) -> Captcha {
-    let mut c = Captcha::new();
-    c.add_random_chars(rnd());
-    match d {
-        Difficulty::Easy => c
-            .apply_filter(Noise::new(0.2))
-            .expect("Noise filter failed")
-            .apply_filter(Grid::new(8, 8))
-            .expect("Grid filter failed"),
-        Difficulty::Medium => c
-            .apply_filter(Noise::new(0.3))
-            .expect("Noise filter failed")
-            .apply_filter(Grid::new(6, 6))
-            .expect("Grid filter failed"),
-        Difficulty::Hard => c
-            .apply_filter(Noise::new(0.5))
-            .expect("Noise filter failed")
-            .apply_filter(Grid::new(4, 4))
-            .expect("Grid filter failed"),
-    };
-    c.apply_filter(Wave::new(2.0, 10.0))
-        .expect("Wave filter failed")
-        .view(WIDTH, HEIGHT);
-    match d {
-        Difficulty::Easy => c.apply_filter(Dots::new(10).max_radius(7).min_radius(3)),
-        Difficulty::Medium => c.apply_filter(Dots::new(15).max_radius(7).min_radius(4)),
-        Difficulty::Hard => c.apply_filter(Dots::new(20).max_radius(7).min_radius(5)),
+/// Returns a CAPTCHA with between 4 and 6 random characters which fit into the view.
+fn captcha_with_chars() -> Captcha {
+    loop {
+        let mut c = Captcha::new();
+        c.add_random_chars(rnd());
+        let a = c.text_area();
+        if a.right - a.left < WIDTH {
+            return c;
+        }
+    }
+}
+
+/// Returns the filters of a predefined CAPTCHA which are applied before and after the CAPTCHA
+/// is cropped to its final size.
+fn filters(d: Difficulty, t: CaptchaName) -> Filters {
+    match t {
+        CaptchaName::Amelia => filters_amelia(d),
+        CaptchaName::Lucy => filters_lucy(d),
+        CaptchaName::Mila => filters_mila(d),
     }
-    .expect("Dots filter failed");
-    c
 }
 
-fn captcha_lucy(d: Difficulty) -> Captcha {
+fn filters_amelia(d: Difficulty) -> Filters {
+    let (n, g, dots) = match d {
+        Difficulty::Easy => (0.2, 8, Dots::new(10).max_radius(7).min_radius(3)),
+        Difficulty::Medium => (0.3, 6, Dots::new(15).max_radius(7).min_radius(4)),
+        Difficulty::Hard => (0.5, 4, Dots::new(20).max_radius(7).min_radius(5)),
+    };
+    (
+        vec![
+            Box::new(Noise::new(n)),
+            Box::new(Grid::new(g, g)),
+            Box::new(Wave::new(2.0, 10.0)),
+        ],
+        vec![Box::new(dots)],
+    )
+}
+
+fn filters_lucy(d: Difficulty) -> Filters {
     let (n, g) = match d {
         Difficulty::Easy => (0.1, 8),
         Difficulty::Medium => (0.4, 6),
         Difficulty::Hard => (0.6, 4),
     };
-
-    let mut c = Captcha::new();
-    c.add_random_chars(rnd())
-        .apply_filter(Noise::new(n))
-        .expect("Noise filter failed")
-        .apply_filter(Grid::new(g, g))
-        .expect("Grid filter failed")
-        .view(WIDTH, HEIGHT);
-    c
+    (
+        vec![Box::new(Noise::new(n)), Box::new(Grid::new(g, g))],
+        vec![],
+    )
 }
 
-fn captcha_mila(d: Difficulty) -> Captcha {
-    let mut c = Captcha::new();
-    c.add_random_chars(rnd());
-    match d {
-        Difficulty::Easy => c
-            .apply_filter(Noise::new(0.2))
-            .expect("Noise filter failed"),
-        Difficulty::Medium => c
-            .apply_filter(Noise::new(0.3))
-            .expect("Noise filter failed"),
-        Difficulty::Hard => c
-            .apply_filter(Noise::new(0.5))
-            .expect("Noise filter failed"),
+fn filters_mila(d: Difficulty) -> Filters {
+    let n = match d {
+        Difficulty::Easy => 0.2,
+        Difficulty::Medium => 0.3,
+        Difficulty::Hard => 0.5,
     };
-    c.apply_filter(Wave::new(2.0, 20.0))
-        .expect("Wave filter failed")
-        .view(WIDTH, HEIGHT)
-        .apply_filter(
+    (
+        vec![Box::new(Noise::new(n)), Box::new(Wave::new(2.0, 20.0))],
+        vec![Box::new(
             Cow::new()
                 .min_radius(40)
                 .max_radius(50)
                 .circles(1)
                 .area(Geometry::new(40, 150, 50, 70)),
-        )
-        .expect("Cow filter failed");
-    c
+        )],
+    )
 }
 
-//fn hard() -> Captcha {
-//    let mut c = Captcha::new();
-//    c.add_chars(rnd())
-//        .apply_filter(Noise::new(0.4))
-//        .apply_filter(Wave::new(2.0, 20.0).horizontal())
-//        .apply_filter(Wave::new(2.0, 20.0).vertical())
-//        .view(WIDTH, HEIGHT)
-//        .apply_filter(Dots::new(15));
-//    c
-//}
+#[cfg(test)]
+mod tests {
+    use filters::{validate_filters, ValidationPolicy};
+    use samples::{filters, CAPTCHA_NAMES, HEIGHT, WIDTH};
+    use Difficulty;
+
+    #[test]
+    fn presets_pass_strict_policy() {
+        let strict = ValidationPolicy::strict();
+        for name in CAPTCHA_NAMES {
+            for d in &[Difficulty::Easy, Difficulty::Medium, Difficulty::Hard] {
+                let (before, after) = filters(*d, *name);
+                assert!(validate_filters(&before, (WIDTH, HEIGHT), &strict).is_empty());
+                assert!(validate_filters(&after, (WIDTH, HEIGHT), &strict).is_empty());
+            }
+        }
+    }
+}
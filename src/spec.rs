@@ -0,0 +1,769 @@
+//! A serializable description of how a CAPTCHA is generated.
+
+use filters::{estimate_cost, pixels, validate_filters, Filter, FilterIssue, ValidationPolicy};
+use fonts::{Default as DefaultFont, Font};
+use images::Image;
+use std::cmp::{max, min};
+use {
+    Error, GeometryError, RngCaptcha, TextPath, VerticalAlign, CANVAS_HEIGHT, CANVAS_WIDTH,
+    MAX_SCALE,
+};
+
+/// A problem which has been found by [`CaptchaSpec::validate`](struct.CaptchaSpec.html#method.validate).
+#[derive(Debug)]
+pub enum SpecIssue {
+    /// A setting of the spec which is not a filter is invalid.
+    Setting(Error),
+    /// A filter which is applied before `view` is invalid.
+    BeforeView(FilterIssue),
+    /// A filter which is applied after `view` is invalid.
+    AfterView(FilterIssue),
+}
+
+impl std::fmt::Display for SpecIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SpecIssue::Setting(e) => e.fmt(f),
+            SpecIssue::BeforeView(i) => write!(f, "before view: {}", i),
+            SpecIssue::AfterView(i) => write!(f, "after view: {}", i),
+        }
+    }
+}
+
+/// Describes the complete pipeline which generates a CAPTCHA.
+///
+/// A spec contains everything that is otherwise configured via the builder methods of
+/// [`RngCaptcha`](struct.RngCaptcha.html): the font, the characters, how they are laid out, the
+/// filters which are applied before and after the CAPTCHA is cropped with `view`, the color and
+/// the background images.
+/// Together with a seeded random number generator `render` reproduces a CAPTCHA exactly.
+///
+/// All fields are optional when a spec is deserialized. Missing fields take the values of
+/// `CaptchaSpec::default()`.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate captcha;
+/// # extern crate rand;
+/// # extern crate serde_json;
+/// use captcha::CaptchaSpec;
+/// use rand::{rngs::StdRng, SeedableRng};
+///
+/// # fn main() -> Result<(), captcha::Error> {
+/// let spec: CaptchaSpec = serde_json::from_str(r#"{
+///     "count": 5,
+///     "view": [220, 120],
+///     "before_view": [{"filter": "Noise", "prob": 0.2}],
+///     "after_view": [{"filter": "Dots", "n": 3, "min_radius": 3, "max_radius": 7}]
+/// }"#).expect("invalid spec");
+/// spec.render(StdRng::seed_from_u64(42))?.as_png()?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct CaptchaSpec {
+    /// The font as JSON which maps each character to a base64 encoded PNG image. If `None` the
+    /// built-in font is used.
+    pub font: Option<String>,
+    /// The characters from which the text is chosen. If `None` all characters of the font are
+    /// used.
+    pub chars: Option<Vec<char>>,
+    /// The number of characters.
+    pub count: u32,
+    /// The range of angles (in degrees) by which characters are rotated.
+    pub rotation: (f32, f32),
+    /// The range of factors by which characters are scaled.
+    pub scale: (f32, f32),
+    /// The spacing in pixels between characters.
+    pub spacing: i32,
+    /// The maximum deviation in pixels from `spacing`.
+    pub spacing_jitter: u32,
+    /// The maximum number of pixels by which characters are moved up or down.
+    pub vertical_jitter: u32,
+    /// How characters are placed onto the line along which the text is written.
+    pub align: VerticalAlign,
+    /// The shape of the line along which the text is written.
+    pub path: TextPath,
+    /// The size of the canvas. If `None` the canvas grows with the text.
+    pub canvas: Option<(u32, u32)>,
+    /// The filters which are applied in order before the CAPTCHA is cropped.
+    pub before_view: Vec<Box<dyn Filter>>,
+    /// The width and height of the final CAPTCHA.
+    pub view: (u32, u32),
+    /// The filters which are applied in order after the CAPTCHA has been cropped.
+    pub after_view: Vec<Box<dyn Filter>>,
+    /// The color of the text.
+    pub color: Option<[u8; 4]>,
+    /// Base64 encoded PNG images of which one is used as background, see
+    /// [`RngCaptcha::set_background_images`](struct.RngCaptcha.html#method.set_background_images).
+    pub backgrounds: Vec<String>,
+}
+
+impl Default for CaptchaSpec {
+    fn default() -> CaptchaSpec {
+        CaptchaSpec {
+            font: None,
+            chars: None,
+            count: 5,
+            rotation: (0.0, 0.0),
+            scale: (1.0, 1.0),
+            spacing: 0,
+            spacing_jitter: 0,
+            vertical_jitter: 0,
+            align: VerticalAlign::Center,
+            path: TextPath::Straight,
+            canvas: None,
+            before_view: vec![],
+            view: (220, 120),
+            after_view: vec![],
+            color: None,
+            backgrounds: vec![],
+        }
+    }
+}
+
+impl CaptchaSpec {
+    /// Checks the spec without rendering it.
+    ///
+    /// The font, the characters, the layout of the text, the size of the canvas and the view and
+    /// every filter are checked. The layout is checked with the limits of `policy`, in particular
+    /// the canvas which the text needs may not be larger than `policy.max_image_size`. All
+    /// filters are validated against the size of the view with the limits of `policy`. All
+    /// problems which are found are returned, the result is empty if the spec is valid.
+    pub fn validate(&self, policy: &ValidationPolicy) -> Vec<SpecIssue> {
+        let mut issues = vec![];
+        match self.font() {
+            Ok(font) => {
+                let supported = font.chars();
+                if let Some(ref chars) = self.chars {
+                    if let Some(c) = chars.iter().find(|c| !supported.contains(c)) {
+                        issues.push(SpecIssue::Setting(Error::UnknownChar(*c)));
+                    }
+                }
+                match self.glyph_size(&font) {
+                    Ok(glyph) => issues.extend(
+                        self.validate_layout(glyph, policy)
+                            .into_iter()
+                            .map(SpecIssue::Setting),
+                    ),
+                    Err(e) => issues.push(SpecIssue::Setting(e)),
+                }
+            }
+            Err(e) => issues.push(SpecIssue::Setting(e)),
+        }
+
+        if let Err(e) = self.validate_backgrounds(policy) {
+            issues.push(SpecIssue::Setting(e));
+        }
+
+        let (w, h) = self.view;
+        if w == 0 || h == 0 {
+            let e = GeometryError::TooSmall {
+                width: w,
+                height: h,
+            };
+            issues.push(SpecIssue::Setting(e.into()));
+        }
+
+        issues.extend(
+            validate_filters(&self.before_view, self.view, policy)
+                .into_iter()
+                .map(SpecIssue::BeforeView),
+        );
+        issues.extend(
+            validate_filters(&self.after_view, self.view, policy)
+                .into_iter()
+                .map(SpecIssue::AfterView),
+        );
+        issues
+    }
+
+    /// Checks that the background images can be decoded, that they are at most
+    /// `policy.max_image_size` pixels wide and high and that one of them covers the view.
+    fn validate_backgrounds(&self, policy: &ValidationPolicy) -> Result<(), Error> {
+        if self.backgrounds.is_empty() {
+            return Ok(());
+        }
+        let max = policy.max_image_size;
+        let mut covered = false;
+        for png in self.backgrounds()? {
+            let i = Image::from_png(png)?;
+            if i.width() > max || i.height() > max {
+                return Err(Error::invalid_setting(
+                    "backgrounds",
+                    format!("must be at most {} pixels wide and high", max),
+                ));
+            }
+            covered |= i.width() >= self.view.0 && i.height() >= self.view.1;
+        }
+        if !covered {
+            return Err(Error::BackgroundTooSmall {
+                width: self.view.0,
+                height: self.view.1,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the decoded PNG images of `backgrounds`.
+    fn backgrounds(&self) -> Result<Vec<Vec<u8>>, Error> {
+        use base64::Engine;
+        self.backgrounds
+            .iter()
+            .map(|b| {
+                base64::engine::general_purpose::STANDARD
+                    .decode(b)
+                    .map_err(|_| Error::invalid_setting("backgrounds", "must be base64 encoded"))
+            })
+            .collect()
+    }
+
+    /// Checks the characters, the settings which determine the layout of the text and the size of
+    /// the images for glyphs of at most `glyph` pixels.
+    fn validate_layout(&self, glyph: (u32, u32), policy: &ValidationPolicy) -> Vec<Error> {
+        let max = policy.max_image_size;
+        let finite = |(a, b): (f32, f32)| a.is_finite() && b.is_finite();
+        let within = |v: u64| v <= max as u64;
+        let size = |(w, h): (u32, u32)| w > 0 && h > 0 && within(w as u64) && within(h as u64);
+        let pixels = format!("must be at most {} pixels", max);
+        let sizes = format!("must be between 1 and {} pixels wide and high", max);
+        let path = match self.path {
+            TextPath::Straight => true,
+            TextPath::Sine { amplitude, period } => {
+                finite((amplitude, period)) && within(amplitude.abs() as u64) && period > 0.0
+            }
+            TextPath::Arc { radius } => radius.is_finite() && within(radius.abs() as u64),
+        };
+
+        let checks = vec![
+            // a CAPTCHA without characters would have an empty answer
+            (
+                self.chars.as_ref().is_none_or(|c| !c.is_empty()),
+                "chars",
+                "must not be empty".to_string(),
+            ),
+            (
+                self.count > 0 && self.count <= policy.max_chars,
+                "count",
+                format!("must be greater than 0 and at most {}", policy.max_chars),
+            ),
+            (
+                finite(self.rotation)
+                    && self.rotation.0.abs() <= 360.0
+                    && self.rotation.1.abs() <= 360.0,
+                "rotation",
+                "must be between -360.0 and 360.0".to_string(),
+            ),
+            (
+                finite(self.scale)
+                    && self.scale.0 > 0.0
+                    && self.scale.1 > 0.0
+                    && self.scale.0.max(self.scale.1) <= MAX_SCALE,
+                "scale",
+                format!("must be greater than 0.0 and at most {}", MAX_SCALE),
+            ),
+            (
+                within(self.spacing.unsigned_abs() as u64),
+                "spacing",
+                pixels.clone(),
+            ),
+            (
+                within(self.spacing_jitter as u64),
+                "spacing_jitter",
+                pixels.clone(),
+            ),
+            (
+                within(self.vertical_jitter as u64),
+                "vertical_jitter",
+                pixels,
+            ),
+            (
+                path,
+                "path",
+                format!(
+                    "must have a positive period and an amplitude or radius of at most {} pixels",
+                    max
+                ),
+            ),
+            (self.canvas.is_none_or(size), "canvas", sizes.clone()),
+            // an empty view is reported as a geometry error
+            (
+                self.view.0 == 0 || self.view.1 == 0 || size(self.view),
+                "view",
+                sizes,
+            ),
+        ];
+        let mut errors: Vec<Error> = checks
+            .into_iter()
+            .filter(|c| !c.0)
+            .map(|(_, field, reason)| Error::invalid_setting(field, reason))
+            .collect();
+
+        // the size of the canvas can only be estimated for valid settings
+        if errors.is_empty() {
+            let (w, h) = self.canvas_bound(glyph);
+            if !within(w) || !within(h) {
+                errors.push(Error::invalid_setting(
+                    "count",
+                    format!(
+                        "the text may need a canvas of {}x{} pixels, at most {} pixels are allowed on each side",
+                        w, h, max
+                    ),
+                ));
+            }
+        }
+        errors
+    }
+
+    /// Returns the font of the spec.
+    fn font(&self) -> Result<DefaultFont, Error> {
+        match self.font {
+            Some(ref json) => DefaultFont::from_json(json).map_err(Error::Font),
+            None => Ok(DefaultFont::new()),
+        }
+    }
+
+    /// Returns the largest width and the largest height of the glyphs of the characters which
+    /// are used.
+    ///
+    /// Returns an error if a glyph cannot be decoded.
+    fn glyph_size(&self, font: &dyn Font) -> Result<(u32, u32), Error> {
+        let chars = match self.chars {
+            Some(ref chars) => chars.clone(),
+            None => font.chars(),
+        };
+        let supported = font.chars();
+        let mut size = (0, 0);
+        // unsupported characters are reported separately
+        for c in chars.into_iter().filter(|c| supported.contains(c)) {
+            // a glyph which is no valid base64 is rejected like an unknown character on render
+            let png = font.png(c).ok_or(Error::UnknownChar(c))?;
+            let i = Image::from_png(png)?;
+            size = (max(size.0, i.width()), max(size.1, i.height()));
+        }
+        Ok(size)
+    }
+
+    /// Returns an upper bound of the width and the height of the canvas on which the text is
+    /// written for glyphs of at most `glyph` pixels.
+    ///
+    /// A fixed canvas keeps its size. Otherwise the canvas grows whenever a character does not
+    /// fit, and it grows by the size of a character in addition to make room for the next ones,
+    /// so that it is at most one character larger than the text on each side.
+    fn canvas_bound(&self, glyph: (u32, u32)) -> (u64, u64) {
+        if let Some((w, h)) = self.canvas {
+            return (w as u64, h as u64);
+        }
+        let scale = self.scale.0.abs().max(self.scale.1.abs());
+        let (mut gw, mut gh) = (
+            (glyph.0 as f32 * scale).ceil() as u64,
+            (glyph.1 as f32 * scale).ceil() as u64,
+        );
+        if self.rotation != (0.0, 0.0) {
+            // a rotated glyph fits into a square whose side is the diagonal of the glyph
+            let d = (gw as f64).hypot(gh as f64).ceil() as u64;
+            gw = d;
+            gh = d;
+        }
+        let advance = gw
+            .saturating_add(self.spacing.unsigned_abs() as u64)
+            .saturating_add(self.spacing_jitter as u64);
+        let offset = match self.path {
+            TextPath::Straight => 0.0,
+            TextPath::Sine { amplitude, .. } => amplitude.abs(),
+            TextPath::Arc { radius } => radius.abs(),
+        } as u64;
+        let offset = offset.saturating_add(self.vertical_jitter as u64);
+        let w = (self.count as u64)
+            .saturating_mul(advance)
+            .saturating_add(gw.saturating_mul(2))
+            .saturating_add(CANVAS_WIDTH as u64);
+        let h = offset
+            .saturating_add(gh.saturating_mul(2))
+            .saturating_mul(2)
+            .saturating_add(CANVAS_HEIGHT as u64);
+        (w, h)
+    }
+
+    /// Returns the estimated number of pixel operations of the spec.
+    ///
+    /// Filters which are applied after `view` are estimated for the size of the view. Filters
+    /// which are applied before are estimated for an upper bound of the size of the canvas. A
+    /// canvas which grows with the text is charged once per character for being enlarged, and
+    /// `view` is charged for enlarging the canvas around the text and for copying the view.
+    ///
+    /// Layout settings which even `ValidationPolicy::permissive()` rejects cost `u64::MAX`.
+    pub fn cost(&self) -> u64 {
+        // a font which cannot be decoded fails to render anyway
+        let glyph = self
+            .font()
+            .and_then(|f| self.glyph_size(&f))
+            .unwrap_or((0, 0));
+        if !self
+            .validate_layout(glyph, &ValidationPolicy::permissive())
+            .is_empty()
+        {
+            return u64::MAX;
+        }
+        let (w, h) = self.canvas_bound(glyph);
+        let size = |w: u64, h: u64| {
+            (
+                min(w, u32::MAX as u64) as u32,
+                min(h, u32::MAX as u64) as u32,
+            )
+        };
+        let canvas = size(w, h);
+        let growth = match self.canvas {
+            Some(_) => pixels(canvas),
+            None => (self.count as u64).saturating_mul(pixels(canvas)),
+        };
+        let padded = size(
+            w.saturating_add(self.view.0 as u64),
+            h.saturating_add(self.view.1 as u64),
+        );
+        [
+            estimate_cost(&self.before_view, canvas),
+            growth,
+            pixels(padded),
+            pixels(self.view),
+            estimate_cost(&self.after_view, self.view),
+        ]
+        .iter()
+        .fold(0, |sum: u64, c| sum.saturating_add(*c))
+    }
+
+    /// Generates the CAPTCHA described by this spec using the random number generator `rng`.
+    ///
+    /// The same spec and an identically seeded generator always result in the same CAPTCHA. The
+    /// layout is checked with `ValidationPolicy::permissive()` first, a setting which is rejected
+    /// results in `Error::InvalidSetting`.
+    pub fn render<R: rand::Rng + rand::RngCore>(&self, rng: R) -> Result<RngCaptcha<R>, Error> {
+        self.render_captcha(RngCaptcha::from_rng(rng))
+    }
+
+    /// Same as `render` but the filters and the resizing of the image may not perform more than
+    /// `budget` pixel operations.
+    ///
+    /// If the estimate of `cost` already exceeds the budget `Error::BudgetExceeded` is returned
+    /// before anything is rendered. Otherwise the budget is enforced for each filter and each copy
+    /// of the image with the actual size of the image, see
+    /// [`RngCaptcha::set_cost_budget`](struct.RngCaptcha.html#method.set_cost_budget).
+    pub fn render_with_budget<R: rand::Rng + rand::RngCore>(
+        &self,
+        rng: R,
+        budget: u64,
+    ) -> Result<RngCaptcha<R>, Error> {
+        let cost = self.cost();
+        if cost > budget {
+            return Err(Error::BudgetExceeded { cost, budget });
+        }
+        let mut c = RngCaptcha::from_rng(rng);
+        c.set_cost_budget(budget);
+        self.render_captcha(c)
+    }
+
+    fn render_captcha<R: rand::Rng + rand::RngCore>(
+        &self,
+        mut c: RngCaptcha<R>,
+    ) -> Result<RngCaptcha<R>, Error> {
+        let font = self.font()?;
+        let glyph = self.glyph_size(&font)?;
+        let permissive = ValidationPolicy::permissive();
+        if let Some(e) = self.validate_layout(glyph, &permissive).into_iter().next() {
+            return Err(e);
+        }
+        if self.font.is_some() {
+            c.set_font(font);
+        }
+        if let Some(ref chars) = self.chars {
+            c.set_chars(chars)?;
+        }
+        if let Some((w, h)) = self.canvas {
+            c.set_canvas_size(w, h)?;
+        }
+        c.set_rotation(self.rotation.0, self.rotation.1)
+            .set_scale_range(self.scale.0, self.scale.1)
+            .set_spacing(self.spacing)
+            .set_spacing_jitter(self.spacing_jitter)
+            .set_vertical_jitter(self.vertical_jitter)
+            .set_vertical_align(self.align.clone())
+            .set_text_path(self.path.clone())
+            .try_add_random_chars(self.count)?;
+
+        for f in &self.before_view {
+            c.apply_filter_dyn(f.as_ref())?;
+        }
+        c.view(self.view.0, self.view.1)?;
+        for f in &self.after_view {
+            c.apply_filter_dyn(f.as_ref())?;
+        }
+        if let Some(color) = self.color {
+            c.set_color(color);
+        }
+        if !self.backgrounds.is_empty() {
+            c.set_background_images(&self.backgrounds()?)?;
+        }
+        Ok(c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::Engine;
+    use filters::{ColorInvert, Cow, Filter, Grid, Noise, ValidationPolicy, Wave};
+    use images::{Image, Pixl};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use {CaptchaSpec, Error, RngCaptcha, SpecIssue, TextPath};
+
+    #[test]
+    fn spec_roundtrip_is_reproducible() {
+        let spec = CaptchaSpec {
+            chars: Some(vec!['a', 'b', 'c', 'x', 'y']),
+            count: 4,
+            rotation: (-20.0, 20.0),
+            path: TextPath::Sine {
+                amplitude: 5.0,
+                period: 80.0,
+            },
+            before_view: vec![Box::new(Noise::new(0.2)), Box::new(Wave::new(2.0, 10.0))],
+            after_view: vec![Box::new(Cow::new())],
+            color: Some([0, 0, 200, 255]),
+            backgrounds: vec![background(300, 200)],
+            ..CaptchaSpec::default()
+        };
+        assert!(spec.validate(&ValidationPolicy::strict()).is_empty());
+        let json = serde_json::to_string(&spec).expect("serialize failed");
+        let copy: CaptchaSpec = serde_json::from_str(&json).expect("deserialize failed");
+
+        let a = spec
+            .render(StdRng::seed_from_u64(3))
+            .expect("render failed");
+        let b = copy
+            .render(StdRng::seed_from_u64(3))
+            .expect("render failed");
+        assert_eq!(a.chars().len(), 4);
+        assert_eq!(a.chars(), b.chars());
+        assert_eq!(a.as_png().expect("no png"), b.as_png().expect("no png"));
+        let png = Image::from_png(a.as_png().expect("no png")).expect("invalid png");
+        assert_eq!(png.get_pixel(0, 0).rgba(), [0, 160, 80, 255]);
+    }
+
+    /// Returns a base64 encoded PNG image of the given size.
+    fn background(w: u32, h: u32) -> String {
+        let mut i = Image::new(w, h);
+        i.fill_under(|_, _| Pixl::new(0, 160, 80));
+        i.as_png()
+            .map(|p| base64::engine::general_purpose::STANDARD.encode(p))
+            .expect("encoding failed")
+    }
+
+    #[test]
+    fn spec_backgrounds() {
+        let strict = ValidationPolicy::strict();
+        let spec = |backgrounds: Vec<String>| CaptchaSpec {
+            backgrounds,
+            ..CaptchaSpec::default()
+        };
+        assert!(matches!(
+            spec(vec![background(100, 100)]).validate(&strict)[..],
+            [SpecIssue::Setting(Error::BackgroundTooSmall {
+                width: 220,
+                height: 120
+            })]
+        ));
+        assert!(matches!(
+            spec(vec!["no base64".to_string()]).validate(&strict)[..],
+            [SpecIssue::Setting(Error::InvalidSetting {
+                field: "backgrounds",
+                ..
+            })]
+        ));
+        assert!(matches!(
+            spec(vec!["iVBORw0KGgo=".to_string()]).render(StdRng::seed_from_u64(0)),
+            Err(Error::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn spec_errors() {
+        let spec: CaptchaSpec = serde_json::from_str(r#"{"chars": ["a", "?"]}"#).unwrap();
+        assert!(matches!(
+            spec.render(StdRng::seed_from_u64(0)),
+            Err(Error::UnknownChar('?'))
+        ));
+        let spec: CaptchaSpec = serde_json::from_str(r#"{"chars": []}"#).unwrap();
+        assert!(matches!(
+            spec.render(StdRng::seed_from_u64(0)),
+            Err(Error::InvalidSetting { field: "chars", .. })
+        ));
+
+        let spec: CaptchaSpec = serde_json::from_str(r#"{"font": "[]"}"#).unwrap();
+        assert!(matches!(
+            spec.render(StdRng::seed_from_u64(0)),
+            Err(Error::Font(_))
+        ));
+
+        // layouts which even the permissive policy rejects are not rendered
+        for json in &[
+            r#"{"rotation": [-3e38, 3e38]}"#,
+            r#"{"scale": [-3e38, 3e38]}"#,
+        ] {
+            let spec: CaptchaSpec = serde_json::from_str(json).expect("invalid spec");
+            assert!(matches!(
+                spec.render(StdRng::seed_from_u64(0)),
+                Err(Error::InvalidSetting { .. })
+            ));
+            assert!(spec
+                .render_with_budget(StdRng::seed_from_u64(0), u64::MAX - 1)
+                .is_err());
+        }
+
+        // glyphs are decoded by the validation and their errors are returned on render
+        let font = r#"{"a": "iVBORw0KGgo=", "b": "no base64"}"#;
+        let spec = CaptchaSpec {
+            font: Some(font.to_string()),
+            chars: Some(vec!['a']),
+            ..CaptchaSpec::default()
+        };
+        let issues = spec.validate(&ValidationPolicy::permissive());
+        assert!(matches!(issues[..], [SpecIssue::Setting(Error::Decode(_))]));
+        assert!(matches!(
+            spec.render(StdRng::seed_from_u64(0)),
+            Err(Error::Decode(_))
+        ));
+        let spec = CaptchaSpec {
+            chars: Some(vec!['b']),
+            ..spec
+        };
+        let issues = spec.validate(&ValidationPolicy::permissive());
+        assert!(matches!(
+            issues[..],
+            [SpecIssue::Setting(Error::UnknownChar('b'))]
+        ));
+
+        // filters are validated before they are applied instead of panicking
+        for filter in &[
+            r#"{"filter": "Grid", "x_gap": 0, "y_gap": 10}"#,
+            r#"{"filter": "Dots", "n": 3, "min_radius": 7, "max_radius": 3}"#,
+            r#"{"filter": "Noise", "prob": 0.1, "mode": {"Clusters": {"min_radius": 5, "max_radius": 2, "color": {"r": 0, "g": 0, "b": 0}}}}"#,
+        ] {
+            let json = format!(r#"{{"after_view": [{}]}}"#, filter);
+            let spec: CaptchaSpec = serde_json::from_str(&json).expect("invalid spec");
+            assert!(matches!(
+                spec.render(StdRng::seed_from_u64(0)),
+                Err(Error::InvalidFilterParams { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn spec_validation() {
+        let spec = CaptchaSpec {
+            chars: Some(vec!['a', '?']),
+            before_view: vec![Box::new(Noise::new(0.2)), Box::new(Noise::new(-1.0))],
+            after_view: vec![Box::new(Grid::new(8, 500))],
+            ..CaptchaSpec::default()
+        };
+        let issues = spec.validate(&ValidationPolicy::strict());
+        assert_eq!(issues.len(), 3);
+        assert!(matches!(
+            issues[0],
+            SpecIssue::Setting(Error::UnknownChar('?'))
+        ));
+        assert!(matches!(issues[1], SpecIssue::BeforeView(ref i) if i.index == 1));
+        assert!(matches!(issues[2], SpecIssue::AfterView(ref i) if i.filter == "Grid"));
+        assert!(CaptchaSpec::default()
+            .validate(&ValidationPolicy::strict())
+            .is_empty());
+    }
+
+    #[test]
+    fn spec_layout_validation() {
+        let strict = ValidationPolicy::strict();
+        let invalid = |json: &str, field: &str| {
+            let spec: CaptchaSpec = serde_json::from_str(json).expect("invalid spec");
+            spec.validate(&strict).iter().any(|i| match i {
+                SpecIssue::Setting(Error::InvalidSetting { field: f, .. }) => *f == field,
+                _ => false,
+            })
+        };
+
+        assert!(invalid(
+            r#"{"spacing_jitter": 4294967295, "vertical_jitter": 4294967295}"#,
+            "spacing_jitter"
+        ));
+        assert!(invalid(r#"{"spacing": 3000, "count": 10}"#, "count"));
+        assert!(invalid(r#"{"count": 0}"#, "count"));
+        assert!(invalid(r#"{"count": 17}"#, "count"));
+        assert!(invalid(r#"{"scale": [0, 0]}"#, "scale"));
+        assert!(invalid(r#"{"scale": [-1, -1]}"#, "scale"));
+        assert!(invalid(r#"{"scale": [1e-30, 3e38]}"#, "scale"));
+        assert!(invalid(
+            r#"{"scale": [1e-30, 3e38], "rotation": [-10, 10]}"#,
+            "scale"
+        ));
+        let spec: CaptchaSpec =
+            serde_json::from_str(r#"{"scale": [1e-30, 3e38], "rotation": [-10, 10]}"#).unwrap();
+        assert_eq!(spec.canvas_bound((40, 60)), (u64::MAX, u64::MAX));
+        assert!(invalid(r#"{"rotation": [-400, 0]}"#, "rotation"));
+        assert!(invalid(r#"{"chars": []}"#, "chars"));
+        assert!(invalid(r#"{"canvas": [100000, 100]}"#, "canvas"));
+        assert!(invalid(r#"{"view": [5000, 100]}"#, "view"));
+        assert!(invalid(
+            r#"{"path": {"Sine": {"amplitude": 10, "period": 0}}}"#,
+            "path"
+        ));
+        assert!(!invalid(
+            r#"{"count": 16, "scale": [0.5, 2], "rotation": [-30, 30], "spacing": -4}"#,
+            "count"
+        ));
+    }
+
+    #[test]
+    fn spec_budget() {
+        let spec = CaptchaSpec {
+            before_view: vec![Box::new(Wave::new(2.0, 10.0))],
+            after_view: (0..10)
+                .map(|_| Box::new(ColorInvert::new()) as Box<dyn Filter>)
+                .collect(),
+            ..CaptchaSpec::default()
+        };
+        let glyph = spec.glyph_size(&spec.font().unwrap()).unwrap();
+        let (w, h) = spec.canvas_bound(glyph);
+        // the filters, enlarging the canvas for each character, padding it and copying the view
+        let view = 220 * 120;
+        let wave = 4 * w * h + w + h;
+        let cost = wave + 5 * w * h + (w + 220) * (h + 120) + view + 10 * view;
+        assert_eq!(spec.cost(), cost);
+        let c = spec
+            .render_with_budget(StdRng::seed_from_u64(0), cost)
+            .expect("render failed");
+        assert!(c.cost() <= cost);
+        assert!(matches!(
+            spec.render_with_budget(StdRng::seed_from_u64(0), cost - 1),
+            Err(Error::BudgetExceeded { .. })
+        ));
+        let spec: CaptchaSpec =
+            serde_json::from_str(r#"{"path": {"Sine": {"amplitude": 1e30, "period": 10}}}"#)
+                .unwrap();
+        assert_eq!(spec.cost(), u64::MAX);
+        assert!(matches!(
+            spec.render_with_budget(StdRng::seed_from_u64(0), 1_000_000),
+            Err(Error::BudgetExceeded { cost: u64::MAX, .. })
+        ));
+
+        let spec = CaptchaSpec {
+            before_view: vec![Box::new(Wave::new(2.0, 10.0))],
+            ..CaptchaSpec::default()
+        };
+        let mut c = RngCaptcha::from_rng(StdRng::seed_from_u64(0));
+        c.set_cost_budget(4 * 400 * 300);
+        assert!(matches!(
+            spec.render_captcha(c),
+            Err(Error::BudgetExceeded { .. })
+        ));
+    }
+}